@@ -0,0 +1,203 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use git2::{Config, Cred, CredentialHelper, CredentialType, RemoteCallbacks};
+
+/// Sources of credentials for fetching repositories.
+///
+/// Sources are tried in order until the remote accepts one: SSH key files,
+/// the token, the git credential helpers, the SSH agent, the fallback SSH key
+/// files and finally the default credentials of the system.
+#[derive(Clone)]
+pub struct Credentials {
+    /// Private SSH key files to try.
+    pub ssh_keys: Vec<SshKey>,
+    /// Private SSH key files to try after the SSH agent, such as the default
+    /// keys in `~/.ssh`.
+    ///
+    /// These come last, as a key that cannot be decrypted fails the fetch
+    /// instead of moving on to the next source.
+    pub fallback_ssh_keys: Vec<SshKey>,
+    /// Token used as the password for HTTPS remotes.
+    pub token: Option<String>,
+    /// Username sent with the token, defaults to the username in the URL.
+    pub username: Option<String>,
+    /// Try the credential helpers configured in git.
+    pub credential_helper: bool,
+    /// Try the keys of the running SSH agent.
+    pub ssh_agent: bool,
+}
+
+/// A private SSH key file.
+#[derive(Clone)]
+pub struct SshKey {
+    pub path: PathBuf,
+    pub passphrase: Option<String>,
+}
+
+impl Default for Credentials {
+    fn default() -> Self {
+        Credentials {
+            ssh_keys: Vec::new(),
+            fallback_ssh_keys: Vec::new(),
+            token: None,
+            username: None,
+            credential_helper: true,
+            ssh_agent: true,
+        }
+    }
+}
+
+// Secrets are left out of debug output, as it ends up in traces.
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("ssh_keys", &self.ssh_keys)
+            .field("fallback_ssh_keys", &self.fallback_ssh_keys)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("username", &self.username)
+            .field("credential_helper", &self.credential_helper)
+            .field("ssh_agent", &self.ssh_agent)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SshKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SshKey")
+            .field("path", &self.path)
+            .field(
+                "passphrase",
+                &self.passphrase.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
+/// A single credential source.
+enum Source<'a> {
+    SshKey(&'a SshKey),
+    Token(&'a str),
+    CredentialHelper,
+    SshAgent,
+    /// The default credentials of the system, used for NTLM and Negotiate.
+    Default,
+}
+
+impl Source<'_> {
+    fn allowed(&self, allowed: CredentialType) -> bool {
+        match self {
+            Source::SshKey(_) | Source::SshAgent => allowed.contains(CredentialType::SSH_KEY),
+            Source::Token(_) | Source::CredentialHelper => {
+                allowed.contains(CredentialType::USER_PASS_PLAINTEXT)
+            }
+            Source::Default => allowed.contains(CredentialType::DEFAULT),
+        }
+    }
+}
+
+impl fmt::Display for Source<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::SshKey(key) => write!(f, "SSH key {}", key.path.display()),
+            Source::Token(_) => write!(f, "token"),
+            Source::CredentialHelper => write!(f, "git credential helper"),
+            Source::SshAgent => write!(f, "SSH agent"),
+            Source::Default => write!(f, "default credentials"),
+        }
+    }
+}
+
+/// The credential sources tried while talking to a remote.
+#[derive(Debug, Default)]
+pub(crate) struct Attempts {
+    /// Descriptions of the sources that were tried.
+    pub tried: Vec<String>,
+    /// Whether every source was tried without success.
+    pub exhausted: bool,
+}
+
+impl Credentials {
+    fn sources(&self) -> Vec<Source<'_>> {
+        let mut sources: Vec<_> = self.ssh_keys.iter().map(Source::SshKey).collect();
+        if let Some(token) = &self.token {
+            sources.push(Source::Token(token));
+        }
+        if self.credential_helper {
+            sources.push(Source::CredentialHelper);
+        }
+        if self.ssh_agent {
+            sources.push(Source::SshAgent);
+        }
+        sources.extend(self.fallback_ssh_keys.iter().map(Source::SshKey));
+        sources.push(Source::Default);
+        sources
+    }
+
+    /// Returns callbacks that try each credential source in turn.
+    ///
+    /// git calls the credentials callback again whenever the remote rejects
+    /// the previous credentials, so every call moves on to the next source.
+    pub(crate) fn callbacks<'a>(&'a self, attempts: Rc<RefCell<Attempts>>) -> RemoteCallbacks<'a> {
+        let sources = self.sources();
+        let mut next = 0;
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |url, username, allowed| {
+            if allowed.contains(CredentialType::USERNAME) {
+                return Cred::username(username.unwrap_or("git"));
+            }
+
+            while let Some(source) = sources.get(next) {
+                next += 1;
+                if !source.allowed(allowed) {
+                    continue;
+                }
+
+                tracing::debug!("trying {source} for {url}");
+                attempts.borrow_mut().tried.push(source.to_string());
+                match self.credential(source, url, username) {
+                    Ok(cred) => return Ok(cred),
+                    Err(err) => tracing::debug!("{source} is not available: {}", err.message()),
+                }
+            }
+
+            attempts.borrow_mut().exhausted = true;
+            Err(git2::Error::from_str("no credentials left to try"))
+        });
+        callbacks
+    }
+
+    fn credential(
+        &self,
+        source: &Source<'_>,
+        url: &str,
+        username: Option<&str>,
+    ) -> Result<Cred, git2::Error> {
+        match source {
+            Source::SshKey(key) => Cred::ssh_key(
+                username.unwrap_or("git"),
+                None,
+                &key.path,
+                key.passphrase.as_deref(),
+            ),
+            Source::Token(token) => {
+                let username = self.username.as_deref().or(username).unwrap_or("git");
+                Cred::userpass_plaintext(username, token)
+            }
+            Source::CredentialHelper => {
+                let config = Config::open_default()?;
+                let (username, password) = CredentialHelper::new(url)
+                    .config(&config)
+                    .username(username)
+                    .execute()
+                    .ok_or_else(|| git2::Error::from_str("no credentials found"))?;
+                Cred::userpass_plaintext(&username, &password)
+            }
+            Source::SshAgent => Cred::ssh_key_from_agent(username.unwrap_or("git")),
+            Source::Default => Cred::default(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::Result;
+use crate::lock::{CacheLock, lock_path};
+use crate::metadata::{Metadata, dir_size};
+use crate::{COMPLETE_MARKER, STAGING_EXTENSION, remove_dir_if_exists};
+
+/// An entry in a cache directory, as found on disk.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// Name of the entry directory, derived from the hash of its source.
+    pub id: String,
+    /// The directory where the entry is stored.
+    pub location: PathBuf,
+    /// Metadata of the entry, missing for entries created by older versions.
+    pub metadata: Option<Metadata>,
+    /// Whether the entry was completely populated.
+    pub complete: bool,
+}
+
+impl CacheEntry {
+    /// Read the entry stored at `location`.
+    pub fn open(location: impl Into<PathBuf>) -> Result<Self> {
+        let location = location.into();
+        let id = location
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(CacheEntry {
+            id,
+            metadata: Metadata::load(&location)?,
+            complete: location.join(COMPLETE_MARKER).is_file(),
+            location,
+        })
+    }
+
+    /// List the entries in the cache directory `root`, sorted by id.
+    ///
+    /// Staging directories of entries being populated are left out.
+    pub fn list(root: &Path) -> Result<Vec<Self>> {
+        Self::read_dir(root, |path| path.extension().is_none())
+    }
+
+    /// List the staging directories in the cache directory `root`, sorted by
+    /// id.
+    ///
+    /// These are only in use while their entry is locked, anything else is
+    /// left behind by an interrupted populate. The lock of a staging directory
+    /// is the lock of its entry.
+    pub fn list_staging(root: &Path) -> Result<Vec<Self>> {
+        Self::read_dir(root, |path| {
+            path.extension()
+                .is_some_and(|extension| extension == STAGING_EXTENSION)
+        })
+    }
+
+    fn read_dir(root: &Path, include: impl Fn(&Path) -> bool) -> Result<Vec<Self>> {
+        let dir = match std::fs::read_dir(root) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries = Vec::new();
+        for entry in dir {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() && include(&entry.path()) {
+                entries.push(CacheEntry::open(entry.path())?);
+            }
+        }
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(entries)
+    }
+
+    /// Size of the entry on disk in bytes.
+    ///
+    /// Uses the size recorded in the metadata if there is any.
+    pub fn size(&self) -> Result<u64> {
+        match &self.metadata {
+            Some(metadata) => Ok(metadata.size),
+            None => dir_size(&self.location),
+        }
+    }
+
+    /// Lock this entry against other processes, waiting at most `timeout`.
+    pub fn lock(&self, timeout: Duration, on_wait: impl FnOnce(Option<u32>)) -> Result<CacheLock> {
+        CacheLock::acquire(lock_path(&self.location), timeout, on_wait)
+    }
+
+    /// Remove the entry from the cache.
+    ///
+    /// The lock file is kept, as other processes might be waiting on it.
+    pub fn remove(&self) -> Result<()> {
+        remove_dir_if_exists(&self.location)?;
+        remove_dir_if_exists(&self.location.with_extension(STAGING_EXTENSION))
+    }
+}
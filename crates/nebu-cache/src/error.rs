@@ -5,11 +5,29 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Error, Debug)]
 pub enum Error {
     #[error(transparent)]
-    GitError(#[from] git2::Error),
+    Git(#[from] git2::Error),
+
+    #[error("failed to authenticate with {url}")]
+    Authentication {
+        url: String,
+        /// Descriptions of the credential sources that were tried.
+        tried: Vec<String>,
+        source: git2::Error,
+    },
+
+    #[error("timed out waiting for the lock on {}", path.display())]
+    LockTimeout {
+        path: std::path::PathBuf,
+        /// Pid of the process holding the lock, if known.
+        pid: Option<u32>,
+    },
+
+    #[error("cannot fetch {url} in offline mode")]
+    Offline { url: String },
 
     #[error(transparent)]
-    IoError(#[from] std::io::Error),
-    
+    Io(#[from] std::io::Error),
+
     #[error(transparent)]
-    Other(#[from] anyhow::Error)
+    Other(#[from] anyhow::Error),
 }
@@ -1,17 +1,44 @@
-use std::{hash::{Hash, Hasher}, path::{Path, PathBuf}};
+use std::{hash::{Hash, Hasher}, path::{Path, PathBuf}, time::Duration};
 use twox_hash::XxHash64;
 
 use crate::error::Result;
+pub use crate::credentials::{Credentials, SshKey};
+pub use crate::entry::CacheEntry;
+pub use crate::error::Error;
+pub use crate::local::{ArchiveCache, ArchiveFormat, LocalCache};
+pub use crate::lock::CacheLock;
+pub use crate::metadata::{
+    HASH_VERSION, METADATA_FILE, Metadata, Source, SourceKind, format_size, now,
+};
+pub use crate::policy::{FreshnessPolicy, parse_duration};
 pub use crate::repo::*;
 
+mod credentials;
+mod entry;
 mod error;
+mod local;
+mod lock;
+mod metadata;
+mod policy;
+mod progress;
 mod repo;
 
 /// Trait for refreshing cached data.
 pub trait Refresh {
+    /// Describe where the cached data comes from.
+    fn source(&self) -> Source;
+    /// Whether the data comes from the local filesystem.
+    ///
+    /// Local sources are checked on every use, regardless of the freshness
+    /// policy and offline mode, and replaced as a whole when refreshed.
+    fn is_local(&self) -> bool {
+        false
+    }
     /// Check if the cached data is fresh at the given location.
     /// 
-    /// This might error if the location is invalid or the data is corrupted.
+    /// This should not use the network, and return `false` if freshness can't
+    /// be known without it. This might error if the location is invalid or the
+    /// data is corrupted.
     fn is_fresh(&self, location: &Path) -> Result<bool>;
     /// Refresh the cached data, without checking if it is already fresh.
     /// 
@@ -29,13 +56,26 @@ pub trait Refresh {
     }
 }
 
+/// Name of the marker file written to complete cache entries.
+pub const COMPLETE_MARKER: &str = ".nebu-complete";
+
+/// Extension of the directory an entry is populated in before it is moved
+/// into place.
+pub(crate) const STAGING_EXTENSION: &str = "staging";
+
 /// A cache manager that can hold different kinds of cached data.
+///
+/// New entries are populated in a staging directory and moved into place once
+/// complete, so an interrupted refresh never leaves a half-populated entry.
+/// Entries without the [`COMPLETE_MARKER`] are treated as missing.
 pub struct CacheManager<T>
 where
     T: Refresh,
 {
     location: PathBuf,
     inner: T,
+    policy: FreshnessPolicy,
+    offline: bool,
 }
 
 impl<T> CacheManager<T>
@@ -48,27 +88,271 @@ where
         inner.hash(&mut hasher);
         let hash = hasher.finish();
         let location = location.join(hash.to_string());
-        Self { location, inner }
+        Self::open(location, inner)
+    }
+
+    /// Manage the existing entry at `location` with the given cache.
+    ///
+    /// Unlike [`CacheManager::new`], the location is not derived from the
+    /// cache, which allows refreshing entries found on disk.
+    pub fn open(location: PathBuf, inner: T) -> Self {
+        Self {
+            location,
+            inner,
+            policy: FreshnessPolicy::default(),
+            offline: false,
+        }
+    }
+
+    /// Never contact the source, serving complete entries as they are.
+    ///
+    /// Refreshing fails with [`Error::Offline`] instead.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Decide when to check the entry for changes with the given policy.
+    pub fn with_policy(mut self, policy: FreshnessPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The directory where this cache entry is stored.
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    /// Lock this entry against other processes, waiting at most `timeout`.
+    ///
+    /// The lock should be held around checking and refreshing the entry, and
+    /// while reading from it. `on_wait` is called with the pid of the holder
+    /// if another process holds the lock.
+    pub fn lock(&self, timeout: Duration, on_wait: impl FnOnce(Option<u32>)) -> Result<CacheLock> {
+        if let Some(parent) = self.location.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        CacheLock::acquire(lock::lock_path(&self.location), timeout, on_wait)
+    }
+
+    /// Read the metadata of this entry, if it has any.
+    pub fn metadata(&self) -> Result<Option<Metadata>> {
+        Metadata::load(&self.location)
+    }
+
+    /// The cache implementation backing this entry.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Checks if the entry was completely populated.
+    pub fn is_complete(&self) -> bool {
+        self.location.join(COMPLETE_MARKER).is_file()
     }
 
-    /// Checks if the cache is fresh.
+    /// Checks if the cache is fresh according to the freshness policy.
     ///
     /// Returns `true` if the data is fresh, `false` otherwise.
     pub fn is_fresh(&self) -> Result<bool> {
-        Ok(self.inner.is_fresh(&self.location)?)
+        if !self.is_complete() {
+            return Ok(false);
+        }
+
+        if self.inner.is_local() {
+            return self.inner.is_fresh(&self.location);
+        }
+        if self.offline {
+            return Ok(true);
+        }
+
+        match self.policy {
+            FreshnessPolicy::Always => {}
+            FreshnessPolicy::Never => return Ok(true),
+            FreshnessPolicy::After(ttl) => {
+                if let Some(metadata) = self.metadata()?
+                    && metadata::now().saturating_sub(metadata.refreshed_at) < ttl.as_secs()
+                {
+                    return Ok(true);
+                }
+            }
+        }
+        self.inner.is_fresh(&self.location)
     }
 
     /// Refresh the cached data, without checking if it's fresh.
     ///
+    /// Incomplete entries are populated from scratch in a staging directory,
+    /// complete ones are refreshed in place.
     /// Returns `true` if the data was refreshed, `false` otherwise.
     pub fn refresh(&mut self) -> Result<bool> {
-        self.inner.refresh(&self.location)
+        let local = self.inner.is_local();
+        if self.offline && !local {
+            return Err(Error::Offline {
+                url: self.inner.source().url,
+            });
+        }
+        if !self.is_complete() || local {
+            return self.populate();
+        }
+
+        // The entry is incomplete while it is refreshed in place, so it is
+        // populated from scratch if the refresh is interrupted.
+        let marker = self.location.join(COMPLETE_MARKER);
+        std::fs::remove_file(&marker)?;
+        let refreshed = self.inner.refresh(&self.location)?;
+        let size = metadata::dir_size(&self.location)?;
+        self.update_metadata(|metadata| metadata.refreshed(size))?;
+        std::fs::File::create(marker)?;
+        Ok(refreshed)
     }
 
     /// Checks if the cache is fresh and refreshes it if not.
     ///
     /// Returns `true` if the data was refreshed, `false` otherwise.
     pub fn try_refresh(&mut self) -> Result<bool> {
-        self.inner.try_refresh(&self.location)
+        if !self.is_fresh()? {
+            self.refresh()
+        } else {
+            self.update_metadata(Metadata::used)?;
+            Ok(false)
+        }
+    }
+
+    /// Update the metadata of this entry, creating it if it is missing.
+    fn update_metadata(&self, update: impl FnOnce(&mut Metadata)) -> Result<()> {
+        let mut metadata = match self.metadata()? {
+            Some(metadata) => metadata,
+            None => Metadata::new(self.inner.source(), metadata::dir_size(&self.location)?),
+        };
+        update(&mut metadata);
+        metadata.write(&self.location)
+    }
+
+    /// Populate the entry in a staging directory and move it into place.
+    ///
+    /// Leftovers of earlier interrupted attempts are removed first, and the
+    /// creation time of a replaced entry is kept. This expects the entry to be
+    /// locked, as the staging directory is shared.
+    fn populate(&mut self) -> Result<bool> {
+        let staging = self.location.with_extension(STAGING_EXTENSION);
+        remove_dir_if_exists(&staging)?;
+        std::fs::create_dir_all(&staging)?;
+        tracing::debug!("populating cache entry in {}", staging.display());
+
+        self.inner.refresh(&staging)?;
+        let mut metadata = Metadata::new(self.inner.source(), metadata::dir_size(&staging)?);
+        if let Some(previous) = self.metadata()? {
+            metadata.created_at = previous.created_at;
+        }
+        metadata.write(&staging)?;
+        std::fs::File::create(staging.join(COMPLETE_MARKER))?;
+
+        remove_dir_if_exists(&self.location)?;
+        std::fs::rename(&staging, &self.location)?;
+        Ok(true)
+    }
+}
+
+/// Remove a directory and its contents, if it exists.
+pub(crate) fn remove_dir_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_dir_all(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cache whose freshness is decided by the test.
+    struct FakeCache {
+        fresh: bool,
+    }
+
+    // Every fake cache manages the same entry, regardless of its freshness.
+    impl Hash for FakeCache {
+        fn hash<H: Hasher>(&self, _state: &mut H) {}
+    }
+
+    impl Refresh for FakeCache {
+        fn source(&self) -> Source {
+            Source::local(SourceKind::Directory, Path::new("/fake"))
+        }
+
+        fn is_fresh(&self, _location: &Path) -> Result<bool> {
+            Ok(self.fresh)
+        }
+
+        fn refresh(&mut self, location: &Path) -> Result<bool> {
+            std::fs::write(location.join("data"), "data")?;
+            Ok(true)
+        }
+    }
+
+    fn manager(root: &Path, fresh: bool) -> CacheManager<FakeCache> {
+        CacheManager::new(root.to_path_buf(), FakeCache { fresh })
+    }
+
+    #[test]
+    fn entries_without_marker_are_missing() {
+        let root = tempfile::tempdir().unwrap();
+        let mut cache = manager(root.path(), true);
+        std::fs::create_dir_all(cache.location()).unwrap();
+        std::fs::write(cache.location().join("partial"), "").unwrap();
+
+        assert!(!cache.is_complete());
+        assert!(!cache.is_fresh().unwrap());
+
+        assert!(cache.try_refresh().unwrap());
+        assert!(cache.is_complete());
+        assert!(cache.location().join("data").is_file());
+        assert!(!cache.location().join("partial").exists());
+        assert!(cache.metadata().unwrap().is_some());
+    }
+
+    #[test]
+    fn refreshing_in_place_keeps_entries_complete() {
+        let root = tempfile::tempdir().unwrap();
+        let mut cache = manager(root.path(), false);
+        cache.refresh().unwrap();
+
+        assert!(cache.refresh().unwrap());
+        assert!(cache.is_complete());
+        assert!(!cache.location().with_extension(STAGING_EXTENSION).exists());
+    }
+
+    #[test]
+    fn after_policy_checks_entries_once_the_ttl_passed() {
+        let root = tempfile::tempdir().unwrap();
+        let ttl = Duration::from_secs(60 * 60);
+        manager(root.path(), false).refresh().unwrap();
+        let cache = manager(root.path(), false).with_policy(FreshnessPolicy::After(ttl));
+
+        assert!(cache.is_fresh().unwrap());
+
+        let mut metadata = cache.metadata().unwrap().unwrap();
+        metadata.refreshed_at = metadata::now() - 2 * ttl.as_secs();
+        metadata.write(cache.location()).unwrap();
+        assert!(!cache.is_fresh().unwrap());
+
+        let cache = manager(root.path(), true).with_policy(FreshnessPolicy::After(ttl));
+        assert!(cache.is_fresh().unwrap());
+    }
+
+    #[test]
+    fn fresh_entries_record_their_use() {
+        let root = tempfile::tempdir().unwrap();
+        manager(root.path(), false).refresh().unwrap();
+        let mut cache = manager(root.path(), false).with_policy(FreshnessPolicy::Never);
+        let mut metadata = cache.metadata().unwrap().unwrap();
+        metadata.used_at = 1;
+        metadata.write(cache.location()).unwrap();
+
+        assert!(!cache.try_refresh().unwrap());
+
+        let metadata = cache.metadata().unwrap().unwrap();
+        assert!(metadata.used_at > 1);
+        assert_eq!(metadata.refreshed_at, metadata.created_at);
     }
 }
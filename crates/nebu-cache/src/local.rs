@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use flate2::read::GzDecoder;
+use git2::Repository;
+
+use crate::error::Result;
+use crate::metadata::{Metadata, Source, SourceKind};
+use crate::{COMPLETE_MARKER, METADATA_FILE, Refresh, remove_dir_if_exists};
+
+/// Directory in a cache entry that archives are unpacked into.
+const UNPACK_DIR: &str = ".nebu-unpack";
+
+/// A template directory on the local filesystem.
+///
+/// The working tree is read as is, including uncommitted changes, so template
+/// authors can try out changes without committing them. Files ignored by the
+/// git repository containing the directory, such as `node_modules`, are left
+/// out. Caching the directory would not save any work, so it is usually read
+/// with [`LocalCache::copy_into`], and cache entries of it are never fresh.
+#[derive(Debug, Clone)]
+pub struct LocalCache {
+    pub path: PathBuf,
+}
+
+/// An archive of a template on the local filesystem.
+///
+/// Archives with a single top-level directory, such as the ones downloaded
+/// from git hosts, are unpacked from within that directory.
+#[derive(Debug, Clone)]
+pub struct ArchiveCache {
+    pub path: PathBuf,
+    pub format: ArchiveFormat,
+}
+
+/// The supported archive formats.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// A gzip compressed tarball, `.tar.gz` or `.tgz`.
+    TarGz,
+    /// A `.zip` archive.
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detect the format of an archive from its file name.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+impl LocalCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        LocalCache { path: path.into() }
+    }
+
+    /// Copies the template directory into `target`, without a cache entry.
+    pub fn copy_into(&self, target: &Path) -> Result<()> {
+        if !self.path.is_dir() {
+            return Err(anyhow::anyhow!(
+                "template directory {} does not exist",
+                self.path.display()
+            )
+            .into());
+        }
+
+        let root = self.path.canonicalize()?;
+        let repo = Repository::discover(&root).ok();
+        let ignored = |path: &Path| {
+            repo.as_ref()
+                .and_then(|repo| {
+                    let relative = path.strip_prefix(repo.workdir()?).ok()?;
+                    repo.is_path_ignored(relative).ok()
+                })
+                .unwrap_or(false)
+        };
+        copy_template(&root, target, &ignored)
+    }
+}
+
+impl ArchiveCache {
+    pub fn new(path: impl Into<PathBuf>, format: ArchiveFormat) -> Self {
+        ArchiveCache {
+            path: path.into(),
+            format,
+        }
+    }
+
+    /// Copies the unpacked template into `target`.
+    pub fn checkout_into(&self, location: &Path, target: &Path) -> Result<()> {
+        copy_template(location, target, &|_| false)
+    }
+
+    /// Unpack the archive into `dir`.
+    fn unpack(&self, dir: &Path) -> Result<()> {
+        let file = File::open(&self.path)?;
+        match self.format {
+            ArchiveFormat::TarGz => tar::Archive::new(GzDecoder::new(file)).unpack(dir)?,
+            ArchiveFormat::Zip => zip::ZipArchive::new(file)
+                .and_then(|mut archive| archive.extract(dir))
+                .map_err(anyhow::Error::from)?,
+        }
+        Ok(())
+    }
+}
+
+// Local sources are hashed with their kind, so a directory and a repository
+// at the same path get different entries.
+impl Hash for LocalCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        "local".hash(state);
+        self.path.hash(state);
+    }
+}
+
+impl Hash for ArchiveCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        "archive".hash(state);
+        self.path.hash(state);
+        self.format.hash(state);
+    }
+}
+
+impl Refresh for LocalCache {
+    fn source(&self) -> Source {
+        Source::local(SourceKind::Directory, &self.path)
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    fn is_fresh(&self, _location: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn refresh(&mut self, location: &Path) -> Result<bool> {
+        self.copy_into(location)?;
+        Ok(true)
+    }
+}
+
+impl Refresh for ArchiveCache {
+    fn source(&self) -> Source {
+        Source::local(SourceKind::Archive, &self.path)
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    /// The entry is fresh if the archive was not modified since it was
+    /// unpacked.
+    fn is_fresh(&self, location: &Path) -> Result<bool> {
+        let Some(metadata) = Metadata::load(location)? else {
+            return Ok(false);
+        };
+        let modified = std::fs::metadata(&self.path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        Ok(modified < metadata.refreshed_at)
+    }
+
+    fn refresh(&mut self, location: &Path) -> Result<bool> {
+        let unpack_dir = location.join(UNPACK_DIR);
+        remove_dir_if_exists(&unpack_dir)?;
+        self.unpack(&unpack_dir)?;
+
+        let mut entries = std::fs::read_dir(&unpack_dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        let root = match entries.as_slice() {
+            [entry] if entry.file_type()?.is_dir() => entries.remove(0).path(),
+            _ => unpack_dir.clone(),
+        };
+        for entry in std::fs::read_dir(&root)? {
+            let entry = entry?;
+            std::fs::rename(entry.path(), location.join(entry.file_name()))?;
+        }
+
+        remove_dir_if_exists(&unpack_dir)?;
+        Ok(true)
+    }
+}
+
+/// Copy a template, leaving out git metadata, the files of the cache and
+/// everything else for which `ignored` returns `true`.
+fn copy_template(from: &Path, to: &Path, ignored: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let skip = |path: &Path| {
+        ignored(path)
+            || path.file_name().is_some_and(|name| {
+                name == ".git"
+                    || name == COMPLETE_MARKER
+                    || name == METADATA_FILE
+                    || name == UNPACK_DIR
+            })
+    };
+    nebu_fs::copy_dir_filtered(from, to, &skip)?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use fs4::fs_std::FileExt;
+
+use crate::error::{Error, Result};
+
+/// How often a held lock is polled while waiting for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An advisory lock on a cache entry, shared between processes.
+///
+/// The lock is released when this is dropped. The lock file holds the pid of
+/// the process holding the lock, so waiting processes can report it.
+#[derive(Debug)]
+pub struct CacheLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl CacheLock {
+    /// Acquire the lock at `path`, waiting at most `timeout` for it.
+    ///
+    /// `on_wait` is called once with the pid of the holder, if known, when
+    /// the lock is held by another process.
+    pub fn acquire(
+        path: impl Into<PathBuf>,
+        timeout: Duration,
+        on_wait: impl FnOnce(Option<u32>),
+    ) -> Result<Self> {
+        let path = path.into();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let start = Instant::now();
+        let mut on_wait = Some(on_wait);
+        while !file.try_lock_exclusive()? {
+            let pid = read_pid(&mut file);
+            if let Some(on_wait) = on_wait.take() {
+                tracing::debug!("waiting for lock {} held by {pid:?}", path.display());
+                on_wait(pid);
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(Error::LockTimeout { path, pid });
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        file.flush()?;
+        tracing::debug!("acquired lock {}", path.display());
+
+        Ok(CacheLock { path, _file: file })
+    }
+
+    /// Path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Path of the lock file for the cache entry at `location`.
+pub(crate) fn lock_path(location: &Path) -> PathBuf {
+    location.with_extension("lock")
+}
+
+/// Read the pid of the process holding the lock.
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_out_while_the_lock_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entry.lock");
+        let held = CacheLock::acquire(&path, Duration::ZERO, |_| {}).unwrap();
+
+        let mut holder = None;
+        let err = CacheLock::acquire(&path, Duration::ZERO, |pid| holder = pid).unwrap_err();
+        assert!(
+            matches!(&err, Error::LockTimeout { path: locked, .. } if *locked == path),
+            "{err:?}"
+        );
+        assert_eq!(holder, Some(std::process::id()));
+
+        drop(held);
+        CacheLock::acquire(&path, Duration::ZERO, |_| {}).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// Name of the metadata file in the root of each cache entry.
+pub const METADATA_FILE: &str = ".nebu-cache.json";
+
+/// Version of the scheme used to derive entry directory names.
+///
+/// This must be bumped whenever the hashed fields change, so entries created
+/// under an older scheme can be told apart.
+pub const HASH_VERSION: u32 = 1;
+
+/// Where the data of a cache entry comes from.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// Kind of the source, which decides how the entry is refreshed.
+    #[serde(default, skip_serializing_if = "SourceKind::is_git")]
+    pub kind: SourceKind,
+    /// URL or path of the source.
+    pub url: String,
+    /// Branch tracked by the entry, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Revision the entry is pinned to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// Remote the branch is fetched from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// Number of commits of history fetched, or the full history if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u32>,
+}
+
+impl Source {
+    /// Describe a source on the local filesystem.
+    pub(crate) fn local(kind: SourceKind, path: &Path) -> Self {
+        Source {
+            kind,
+            url: path.display().to_string(),
+            branch: None,
+            revision: None,
+            remote: None,
+            depth: None,
+        }
+    }
+}
+
+/// The kinds of sources cache entries are populated from.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    /// A git repository, see [`RepoCache`](crate::RepoCache).
+    #[default]
+    Git,
+    /// A local directory, see [`LocalCache`](crate::LocalCache).
+    Directory,
+    /// A local archive, see [`ArchiveCache`](crate::ArchiveCache).
+    Archive,
+}
+
+impl SourceKind {
+    fn is_git(&self) -> bool {
+        *self == SourceKind::Git
+    }
+}
+
+/// Describes a cache entry, so it can be inspected without reading its data.
+///
+/// Times are in seconds since the Unix epoch.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Metadata {
+    /// Version of the scheme used to derive the entry directory name.
+    pub hash_version: u32,
+    /// Where the data of the entry comes from.
+    pub source: Source,
+    /// When the entry was first populated.
+    pub created_at: u64,
+    /// When the entry was last refreshed.
+    pub refreshed_at: u64,
+    /// When the entry was last used.
+    pub used_at: u64,
+    /// Size of the entry on disk in bytes.
+    pub size: u64,
+}
+
+impl Metadata {
+    /// Create the metadata for an entry populated just now.
+    pub fn new(source: Source, size: u64) -> Self {
+        let now = now();
+        Metadata {
+            hash_version: HASH_VERSION,
+            source,
+            created_at: now,
+            refreshed_at: now,
+            used_at: now,
+            size,
+        }
+    }
+
+    /// Read the metadata of the entry at `location`, if it has any.
+    pub fn load(location: &Path) -> Result<Option<Self>> {
+        let contents = match std::fs::read(location.join(METADATA_FILE)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let metadata = serde_json::from_slice(&contents).map_err(std::io::Error::other)?;
+        Ok(Some(metadata))
+    }
+
+    /// Write the metadata to the entry at `location`.
+    ///
+    /// The file is replaced atomically, so readers never see partial metadata.
+    pub fn write(&self, location: &Path) -> Result<()> {
+        let contents = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        let tmp = location.join(format!("{METADATA_FILE}.tmp"));
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(tmp, location.join(METADATA_FILE))?;
+        Ok(())
+    }
+
+    /// Record that the entry was refreshed, updating its size.
+    pub fn refreshed(&mut self, size: u64) {
+        self.refreshed_at = now();
+        self.used_at = self.refreshed_at;
+        self.size = size;
+    }
+
+    /// Record that the entry was used.
+    pub fn used(&mut self) {
+        self.used_at = now();
+    }
+}
+
+/// The current time in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// The total size of the files below `path` in bytes.
+pub(crate) fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Format a size in bytes with binary units, such as `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> Source {
+        Source {
+            kind: SourceKind::Git,
+            url: "https://example.com/template.git".to_string(),
+            branch: Some("main".to_string()),
+            revision: None,
+            remote: Some("origin".to_string()),
+            depth: Some(1),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_the_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Metadata::load(dir.path()).unwrap().is_none());
+
+        let metadata = Metadata::new(source(), 42);
+        metadata.write(dir.path()).unwrap();
+
+        let loaded = Metadata::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.source, source());
+        assert_eq!(loaded.hash_version, HASH_VERSION);
+        assert_eq!(loaded.created_at, metadata.created_at);
+        assert_eq!(loaded.size, 42);
+        assert!(!dir.path().join(format!("{METADATA_FILE}.tmp")).exists());
+    }
+
+    #[test]
+    fn git_sources_are_written_without_kind() {
+        let json = serde_json::to_value(source()).unwrap();
+        assert!(json.get("kind").is_none());
+
+        let local = Source::local(SourceKind::Archive, Path::new("/tmp/template.zip"));
+        let json = serde_json::to_value(&local).unwrap();
+        assert_eq!(json["kind"], "archive");
+    }
+
+    #[test]
+    fn used_only_updates_last_use() {
+        let mut metadata = Metadata::new(source(), 42);
+        metadata.refreshed_at = 1;
+        metadata.used_at = 1;
+
+        metadata.used();
+
+        assert!(metadata.used_at >= metadata.created_at);
+        assert_eq!(metadata.refreshed_at, 1);
+        assert_eq!(metadata.size, 42);
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// When a [`CacheManager`](crate::CacheManager) checks its entry for changes.
+///
+/// Checking a tracked branch for changes requires a fetch, so the policy
+/// decides how often the network is used. Missing entries are always
+/// populated, regardless of the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FreshnessPolicy {
+    /// Check for changes on every use.
+    #[default]
+    Always,
+    /// Never check existing entries for changes.
+    Never,
+    /// Check for changes once the entry was last refreshed this long ago.
+    After(Duration),
+}
+
+impl FromStr for FreshnessPolicy {
+    type Err = String;
+
+    /// Parse `always`, `never` or a duration such as `30m` or `1d`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "always" => Ok(FreshnessPolicy::Always),
+            "never" | "offline" => Ok(FreshnessPolicy::Never),
+            _ => parse_duration(value)
+                .map(FreshnessPolicy::After)
+                .map_err(|_| {
+                    format!("expected `always`, `never` or a duration such as `1h`, got `{value}`")
+                }),
+        }
+    }
+}
+
+impl fmt::Display for FreshnessPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FreshnessPolicy::Always => write!(f, "always"),
+            FreshnessPolicy::Never => write!(f, "never"),
+            FreshnessPolicy::After(ttl) => write!(f, "{}s", ttl.as_secs()),
+        }
+    }
+}
+
+/// Parse a duration such as `30d`, with the units `s`, `m`, `h`, `d` and `w`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("expected a duration such as `30d`, got `{value}`"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown unit `{unit}`, expected one of s, m, h, d or w"
+            ));
+        }
+    };
+    Ok(Duration::from_secs(amount.saturating_mul(seconds)))
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use console::{Term, style};
+use git2::Progress;
+
+use crate::metadata::format_size;
+
+/// How long an operation runs before its progress is shown, so quick ones
+/// don't flash a progress bar.
+const SHOW_AFTER: Duration = Duration::from_millis(250);
+
+/// How often the progress bar is redrawn at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Number of characters in the bar itself.
+const BAR_WIDTH: usize = 24;
+
+/// A progress bar for fetching and checking out repositories.
+///
+/// The bar is drawn on a single line of stderr, which is cleared again when
+/// this is dropped.
+pub(crate) struct ProgressBar {
+    term: Term,
+    started: Instant,
+    drawn_at: Option<Instant>,
+}
+
+impl ProgressBar {
+    pub(crate) fn new() -> Self {
+        ProgressBar {
+            term: Term::stderr(),
+            started: Instant::now(),
+            drawn_at: None,
+        }
+    }
+
+    /// Show the progress of a transfer from a remote.
+    ///
+    /// Objects are indexed while they are received, after which the deltas
+    /// between them are resolved.
+    pub(crate) fn transfer(&mut self, progress: &Progress<'_>) {
+        let received = progress.received_objects();
+        let total = progress.total_objects();
+        if total > 0 && received == total && progress.total_deltas() > 0 {
+            let detail = format!(
+                "{}/{} deltas",
+                progress.indexed_deltas(),
+                progress.total_deltas()
+            );
+            self.draw(
+                "resolving",
+                progress.indexed_deltas(),
+                progress.total_deltas(),
+                &detail,
+            );
+        } else {
+            let detail = format!(
+                "{received}/{total} objects, {}, {} indexed",
+                format_size(progress.received_bytes() as u64),
+                progress.indexed_objects()
+            );
+            self.draw("fetching", received, total, &detail);
+        }
+    }
+
+    /// Show the progress of a checkout.
+    pub(crate) fn checkout(&mut self, current: usize, total: usize) {
+        self.draw(
+            "checkout",
+            current,
+            total,
+            &format!("{current}/{total} files"),
+        );
+    }
+
+    fn draw(&mut self, label: &str, current: usize, total: usize, detail: &str) {
+        let now = Instant::now();
+        if now.duration_since(self.started) < SHOW_AFTER
+            || self
+                .drawn_at
+                .is_some_and(|drawn_at| now.duration_since(drawn_at) < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.drawn_at = Some(now);
+
+        let filled = (current * BAR_WIDTH).checked_div(total).unwrap_or(0);
+        let bar = format!(
+            "{}{}",
+            "=".repeat(filled.min(BAR_WIDTH)),
+            " ".repeat(BAR_WIDTH - filled.min(BAR_WIDTH))
+        );
+        let line = format!(
+            "{:>10} [{bar}] {detail}",
+            style(label).cyan().bold().for_stderr()
+        );
+        let width = usize::from(self.term.size().1);
+
+        // Progress is best effort, so failing to draw it is not an error.
+        let _ = self.term.clear_line();
+        let _ = self
+            .term
+            .write_str(&console::truncate_str(&line, width, "…"));
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        if self.drawn_at.is_some() {
+            let _ = self.term.clear_line();
+        }
+    }
+}
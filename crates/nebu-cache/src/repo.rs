@@ -1,18 +1,62 @@
+use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
+use std::rc::Rc;
 
 use git2::build::{CheckoutBuilder, RepoBuilder};
-use git2::{
-    BranchType, Config, Cred, CredentialHelper, CredentialType, FetchOptions, Oid, RemoteCallbacks, Repository
-};
+use git2::{AutotagOption, BranchType, ErrorCode, FetchOptions, Oid, Repository, ResetType};
 
-use crate::Refresh;
-use crate::error::Result;
+use crate::credentials::{Attempts, Credentials};
+use crate::error::{Error, Result};
+use crate::progress::ProgressBar;
+use crate::{Refresh, Source, SourceKind};
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone)]
 pub struct RepoCache {
     pub repo: String,
     pub branch: String,
     pub remote: String,
+    /// Pins the cache to a fixed revision instead of the head of `branch`.
+    pub revision: Option<Revision>,
+    /// Number of commits of history to fetch, or the full history if `None`.
+    ///
+    /// This is ignored when pinned to a commit, as the commit might not be
+    /// within the given depth of any branch.
+    pub depth: Option<u32>,
+    /// Paths to check out, relative to the repository root.
+    ///
+    /// Everything is checked out if empty. Folders include everything below
+    /// them.
+    pub sparse_paths: Vec<String>,
+    /// Credentials used when talking to the remote.
+    pub credentials: Credentials,
+    /// Show a progress bar on stderr while fetching and checking out.
+    pub progress: bool,
+}
+
+// The sparse paths only affect what is checked out, so caches with different
+// sparse paths share the same objects. Credentials and progress reporting
+// don't affect the contents.
+impl Hash for RepoCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.repo.hash(state);
+        self.branch.hash(state);
+        self.remote.hash(state);
+        self.revision.hash(state);
+        self.depth().hash(state);
+    }
+}
+
+/// A fixed revision of a repository.
+///
+/// Pinned revisions are treated as immutable, so a cache holding one is never
+/// fetched again once the revision is present.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Revision {
+    /// A tag, which is resolved to the commit it points to.
+    Tag(String),
+    /// A full commit OID.
+    Commit(Oid),
 }
 
 impl RepoCache {
@@ -25,9 +69,115 @@ impl RepoCache {
             repo: repo.into(),
             branch: branch.into(),
             remote: remote.into(),
+            revision: None,
+            depth: None,
+            sparse_paths: Vec::new(),
+            credentials: Credentials::default(),
+            progress: false,
+        }
+    }
+
+    /// Use the given credentials when talking to the remote.
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Show a progress bar on stderr while fetching and checking out.
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Fetch only the given number of commits of history.
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Only check out the given paths, relative to the repository root.
+    pub fn with_sparse_paths<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.sparse_paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The depth to fetch with, taking pinned commits into account.
+    fn depth(&self) -> Option<u32> {
+        match self.revision {
+            Some(Revision::Commit(_)) => None,
+            _ => self.depth,
         }
     }
 
+    /// Returns fetch options with authentication, the configured depth and
+    /// progress reporting if enabled.
+    ///
+    /// The credential sources tried are recorded in `attempts`.
+    fn fetch_options(&self, attempts: &Rc<RefCell<Attempts>>) -> FetchOptions<'_> {
+        let mut callbacks = self.credentials.callbacks(attempts.clone());
+        if self.progress {
+            let mut bar = ProgressBar::new();
+            callbacks.transfer_progress(move |progress| {
+                bar.transfer(&progress);
+                true
+            });
+        }
+
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks);
+        if self.revision.is_some() {
+            options.download_tags(AutotagOption::All);
+        }
+        if let Some(depth) = self.depth() {
+            options.depth(depth.try_into().unwrap_or(i32::MAX));
+        }
+        options
+    }
+
+    /// Returns a checkout builder limited to the sparse paths.
+    fn checkout_builder(&self) -> CheckoutBuilder<'_> {
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force();
+        if self.progress {
+            let mut bar = ProgressBar::new();
+            checkout.progress(move |_, current, total| bar.checkout(current, total));
+        }
+        for path in &self.sparse_paths {
+            // Escaped pathspecs are not matched as prefixes, so folders need
+            // an explicit pattern for their contents.
+            let path = escape_pathspec(path.trim_end_matches('/'));
+            checkout.path(format!("{path}/*"));
+            checkout.path(path);
+        }
+        checkout
+    }
+
+    /// Pin the cache to a fixed revision.
+    pub fn with_revision(mut self, revision: Revision) -> Self {
+        self.revision = Some(revision);
+        self
+    }
+
+    /// Returns the OID of the commit the cache points to.
+    ///
+    /// This is the pinned revision if any, otherwise the local head of the
+    /// tracked branch.
+    pub fn resolve(&self, repo: &Repository) -> Result<Oid> {
+        let oid = match &self.revision {
+            Some(Revision::Tag(tag)) => repo
+                .find_reference(&format!("refs/tags/{tag}"))?
+                .peel_to_commit()?
+                .id(),
+            Some(Revision::Commit(oid)) => repo.find_commit(*oid)?.id(),
+            None => self.get_local_and_remote_oids(repo)?.0,
+        };
+        Ok(oid)
+    }
+
     /// Returns the local and remote OIDs of the specified branch in the repository.
     pub fn get_local_and_remote_oids(&self, repo: &Repository) -> Result<(Oid, Oid)> {
         let branch = repo.find_branch(&self.branch, BranchType::Local)?;
@@ -45,72 +195,93 @@ impl RepoCache {
         Ok((local_oid, remote_oid))
     }
 
-    /// Returns a RemoteCallbacks instance that handles authentication.
-    pub fn get_callbacks<'a>() -> RemoteCallbacks<'a> {
-        let mut callbacks = RemoteCallbacks::new();
-        callbacks.credentials(|url, username, allowed_types| {
-            // Prepare the authentication callbacks.
-            //
-            // We check the `allowed` types of credentials, and we try to do as much as
-            // possible based on that:
-            //
-            // * Prioritize SSH keys from the local ssh agent as they're likely the most
-            //   reliable. The username here is prioritized from the credential
-            //   callback, then from whatever is configured in git itself, and finally
-            //   we fall back to the generic user of `git`.
-            //
-            // * If a username/password is allowed, then we fallback to git2-rs's
-            //   implementation of the credential helper. This is what is configured
-            //   with `credential.helper` in git, and is the interface for the OSX
-            //   keychain, for example.
-            //
-            // * After the above two have failed, we just kinda grapple attempting to
-            //   return *something*.
-            let mut cred_helper = CredentialHelper::new(url);
-            let cfg = &Config::open_default()?;
-            cred_helper.config(cfg);
-            if allowed_types.contains(CredentialType::SSH_KEY) {
-                let mut attempts = vec![String::from("git")];
-                if let Some(s) = cred_helper.username {
-                    attempts.push(s);
-                }
-
-                while let Some(s) = attempts.pop() {
-                    return git2::Cred::ssh_key_from_agent(&s);
-                }
-            }
+    /// Clones the repository to the specified location.
+    ///
+    /// This adds remote callbacks to handle authentication and fetch options.
+    pub fn clone_repository(&self, location: &Path) -> Result<Repository> {
+        self.authenticate(|options| {
+            let mut builder = RepoBuilder::new();
+            builder.fetch_options(options);
+            builder.with_checkout(self.checkout_builder());
+            builder.branch(&self.branch);
+            builder.clone(&self.repo, location)
+        })
+    }
 
-            if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
-                let username = username.unwrap_or("git");
-                return Cred::userpass_plaintext(username, "");
-            }
+    /// Fetches the remote of the repository, including all tags if pinned.
+    fn fetch(&self, repo: &Repository) -> Result<()> {
+        let mut remote = repo.find_remote(&self.remote)?;
 
+        let refspecs = remote.fetch_refspecs()?;
+        let collect = refspecs
+            .iter()
+            .map(|refspec| refspec.unwrap())
+            .collect::<Vec<&str>>();
+        self.authenticate(|mut options| remote.fetch(&collect, Some(&mut options), None))
+    }
 
-            if allowed_types.contains(CredentialType::DEFAULT) {
-                return Cred::default()
+    /// Runs `operation` with authenticated fetch options.
+    ///
+    /// Authentication failures are turned into [`Error::Authentication`],
+    /// listing the credential sources that were tried.
+    fn authenticate<T>(
+        &self,
+        operation: impl FnOnce(FetchOptions<'_>) -> std::result::Result<T, git2::Error>,
+    ) -> Result<T> {
+        let attempts = Rc::new(RefCell::new(Attempts::default()));
+        operation(self.fetch_options(&attempts)).map_err(|source| {
+            let attempts = attempts.take();
+            if source.code() == ErrorCode::Auth || attempts.exhausted {
+                Error::Authentication {
+                    url: self.repo.clone(),
+                    tried: attempts.tried,
+                    source,
+                }
+            } else {
+                source.into()
             }
-
-            Err(git2::Error::from_str("no suitable credentials found"))
-        });
-        callbacks
+        })
     }
 
-    /// Clones the repository to the specified location.
+    /// Checks out the tracked revision of the cached repository into `target`.
     ///
-    /// This adds remote callbacks to handle authentication and fetch options.
-    pub fn clone_repository(
-        &self,
-        location: &Path,
-    ) -> std::result::Result<Repository, git2::Error> {
-        let mut builder = RepoBuilder::new();
-        let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(Self::get_callbacks());
-        builder.fetch_options(fetch_options);
-        builder.clone(&self.repo, location)
+    /// The cached repository itself is left untouched, which makes this safe to
+    /// use for producing throwaway copies of the repository contents. Returns
+    /// the OID of the commit that was checked out.
+    pub fn checkout_into(&self, location: &Path, target: &Path) -> Result<Oid> {
+        let repo = Repository::open(location)?;
+        let oid = self.resolve(&repo)?;
+        self.checkout_commit(&repo, oid, target)?;
+        Ok(oid)
+    }
+
+    fn checkout_commit(&self, repo: &Repository, oid: Oid, target: &Path) -> Result<()> {
+        let commit = repo.find_commit(oid)?;
+
+        let mut checkout = self.checkout_builder();
+        checkout.target_dir(target);
+        repo.checkout_tree(commit.as_object(), Some(&mut checkout))?;
+
+        Ok(())
     }
 }
 
 impl Refresh for RepoCache {
+    fn source(&self) -> Source {
+        let revision = self.revision.as_ref().map(|revision| match revision {
+            Revision::Tag(tag) => tag.clone(),
+            Revision::Commit(oid) => oid.to_string(),
+        });
+        Source {
+            kind: SourceKind::Git,
+            url: self.repo.clone(),
+            branch: Some(self.branch.clone()),
+            revision,
+            remote: Some(self.remote.clone()),
+            depth: self.depth(),
+        }
+    }
+
     fn is_fresh(&self, location: &Path) -> Result<bool> {
         if !location.exists() || !location.is_dir() {
             return Ok(false);
@@ -119,7 +290,7 @@ impl Refresh for RepoCache {
         let repo = match Repository::open(location) {
             Ok(repo) => Ok(repo),
             Err(err) => {
-                if err.code() == git2::ErrorCode::NotFound {
+                if err.code() == ErrorCode::NotFound {
                     return Ok(false);
                 } else {
                     Err(err)
@@ -127,60 +298,75 @@ impl Refresh for RepoCache {
             }
         }?;
 
-        let (local_oid, remote_oid) = self.get_local_and_remote_oids(&repo)?;
-        let (ahead, behind) = repo.graph_ahead_behind(local_oid, remote_oid)?;
-
-        if ahead > 0 || behind > 0 {
-            return Ok(false);
+        if self.revision.is_some() {
+            return Ok(self.resolve(&repo).is_ok());
         }
 
-        Ok(true)
+        // Whether a tracked branch is up to date can only be known by fetching
+        // it, so the freshness policy of the cache manager decides when to.
+        Ok(false)
     }
 
     fn refresh(&mut self, location: &Path) -> Result<bool> {
         if !location.exists() || !location.is_dir() {
-            std::fs::create_dir_all(&location)?;
+            std::fs::create_dir_all(location)?;
         }
 
-        let repo = match Repository::open(location) {
-            Ok(repo) => Ok(repo),
-            Err(err) => {
-                if err.code() == git2::ErrorCode::NotFound {
-                    self.clone_repository(location)
-                } else {
-                    Err(err)
-                }
+        let (repo, cloned) = match Repository::open(location) {
+            Ok(repo) => (repo, false),
+            Err(err) if err.code() == ErrorCode::NotFound => {
+                (self.clone_repository(location)?, true)
             }
-        }?;
+            Err(err) => return Err(err.into()),
+        };
 
-        let mut remote = repo.find_remote(&self.remote)?;
+        if self.revision.is_some() {
+            let oid = match self.resolve(&repo) {
+                Ok(oid) => oid,
+                Err(_) => {
+                    self.fetch(&repo)?;
+                    self.resolve(&repo)?
+                }
+            };
+            let moved = cloned || repo.head()?.peel_to_commit()?.id() != oid;
+            repo.set_head_detached(oid)?;
+            repo.checkout_head(Some(&mut self.checkout_builder()))?;
+            return Ok(moved);
+        }
+        if cloned {
+            return Ok(true);
+        }
 
-        let callbacks = RepoCache::get_callbacks();
-        let mut options = FetchOptions::new();
-        options.remote_callbacks(callbacks);
+        self.fetch(&repo)?;
 
-        let refspecs = remote.fetch_refspecs()?;
-        let collect = refspecs
-            .iter()
-            .map(|refspec| refspec.unwrap())
-            .collect::<Vec<&str>>();
-        remote.fetch(&collect, Some(&mut options), None)?;
-
-        let fetch_head = repo.find_reference("FETCH_HEAD")?;
-        let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
-
-        let analysis = repo.merge_analysis(&[&fetch_commit])?;
-        if analysis.0.is_fast_forward() {
-            let head = repo.head()?;
-            let refname = head
-                .name()
-                .ok_or(git2::Error::from_str("No HEAD reference found"))?;
-            let mut reference = repo.find_reference(refname)?;
-            reference.set_target(fetch_commit.id(), "Fast-forward")?;
-            repo.set_head(refname)?;
-            repo.checkout_head(Some(CheckoutBuilder::default().force()))?;
+        // Shallow fetches graft the fetched commit, so it shares no history
+        // with the old head and can never be fast-forwarded to. The cached
+        // repository is never modified, so the branch is reset instead.
+        let fetched = repo.find_reference("FETCH_HEAD")?.peel_to_commit()?;
+        if repo.head()?.peel_to_commit()?.id() == fetched.id() {
+            return Ok(false);
         }
+        repo.reset(
+            fetched.as_object(),
+            ResetType::Hard,
+            Some(&mut self.checkout_builder()),
+        )?;
 
         Ok(true)
     }
 }
+
+/// Escape a path so it is matched literally when used as a pathspec.
+///
+/// Template paths often contain placeholders such as `[name]`, which would
+/// otherwise be treated as a character class.
+fn escape_pathspec(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '[' | ']' | '*' | '?' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum Error {
+    #[error("failed to read the configuration at {}", path.display())]
+    #[diagnostic(code(config::read_error))]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("the configuration at {} is invalid", path.display())]
+    #[diagnostic(
+        code(config::invalid),
+        help("Check the configuration file against the documentation")
+    )]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+}
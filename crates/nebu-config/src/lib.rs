@@ -1,8 +1,34 @@
+use std::path::{Path, PathBuf};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+pub use crate::error::{Error, Result};
+
+mod error;
+
+/// Name of the configuration file in the configuration directory.
+pub const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Config {
-    pub github: GithubConfig,
+    #[serde(default)]
+    pub github: Option<GithubConfig>,
+    /// Credentials used to fetch template repositories.
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    /// How the template cache is used.
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+/// How the template cache is used.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CacheConfig {
+    /// When to check cached templates for changes.
+    ///
+    /// Either `always`, `never` or a duration since the last check, such as
+    /// `1h` or `1d`.
+    pub refresh: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -10,8 +36,68 @@ pub struct GithubConfig {
     pub organization: String,
 }
 
+/// Credentials used to fetch template repositories.
+///
+/// Sources are tried in order: SSH key files, the token, git credential
+/// helpers and finally the SSH agent.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CredentialsConfig {
+    /// Private SSH key files to try.
+    pub ssh_keys: Vec<SshKeyConfig>,
+    /// Token used as the password for HTTPS remotes.
+    ///
+    /// Prefer `token_env` to keep the token out of the configuration file.
+    pub token: Option<String>,
+    /// Environment variable to read the token from.
+    pub token_env: Option<String>,
+    /// Username sent with the token.
+    pub username: Option<String>,
+    /// Try the credential helpers configured in git.
+    pub credential_helper: bool,
+    /// Try the keys of the running SSH agent.
+    pub ssh_agent: bool,
+}
+
+/// A private SSH key file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SshKeyConfig {
+    /// Path to the private key.
+    pub path: PathBuf,
+    /// Environment variable to read the passphrase of the key from.
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+}
+
+impl Default for CredentialsConfig {
+    fn default() -> Self {
+        CredentialsConfig {
+            ssh_keys: Vec::new(),
+            token: None,
+            token_env: None,
+            username: None,
+            credential_helper: true,
+            ssh_agent: true,
+        }
+    }
+}
+
 impl Config {
     pub fn template() -> String {
         "".to_string()
     }
+
+    /// Read the configuration from the given configuration directory.
+    ///
+    /// Returns the default configuration if there is no configuration file.
+    pub fn load_from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(CONFIG_FILE);
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => return Err(Error::Read { path, source }),
+        };
+
+        toml::from_str(&source).map_err(|source| Error::Parse { path, source })
+    }
 }
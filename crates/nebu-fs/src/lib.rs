@@ -1,3 +1,4 @@
+use std::io;
 use std::path::{Path, PathBuf};
 
 /// Takes a path and expands the home directory if it starts with `~` or `~/`.
@@ -14,3 +15,32 @@ pub fn expand_home_dir<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
     
     Some(path.to_path_buf())
 }
+
+/// Recursively copies the contents of the `from` directory into `to`, leaving
+/// out every file and directory for which `skip` returns `true`.
+///
+/// Missing directories are created, and existing files are overwritten.
+pub fn copy_dir_filtered<P, Q, F>(from: P, to: Q, skip: &F) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+{
+    let (from, to) = (from.as_ref(), to.as_ref());
+    std::fs::create_dir_all(to)?;
+
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        if skip(&entry.path()) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_filtered(entry.path(), target, skip)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// The answer to a single question.
+///
+/// Other scalars, such as numbers, are read as strings, so `version: 1` in an
+/// answers file answers a string question.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Answer {
+    Bool(bool),
+    String(String),
+}
+
+impl Answer {
+    /// Returns the answer as a boolean, if it is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Answer::Bool(value) => Some(*value),
+            Answer::String(_) => None,
+        }
+    }
+
+    /// Returns the answer as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Answer::Bool(_) => None,
+            Answer::String(value) => Some(value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Answer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct AnswerVisitor;
+
+        impl Visitor<'_> for AnswerVisitor {
+            type Value = Answer;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a boolean, string or number")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> std::result::Result<Answer, E> {
+                Ok(Answer::Bool(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> std::result::Result<Answer, E> {
+                Ok(Answer::String(value.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> std::result::Result<Answer, E> {
+                Ok(Answer::String(value.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> std::result::Result<Answer, E> {
+                Ok(Answer::String(value.to_string()))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> std::result::Result<Answer, E> {
+                Ok(Answer::String(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(AnswerVisitor)
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Bool(value) => write!(f, "{value}"),
+            Answer::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Answers to the questions of a template, keyed by [`Question::key`].
+///
+/// [`Question::key`]: crate::Question::key
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Answers {
+    values: BTreeMap<String, Answer>,
+}
+
+impl Answers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read answers from a YAML file mapping question keys to answers.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let read_err = |source: Box<dyn std::error::Error + Send + Sync>| Error::ReadAnswers {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let source = std::fs::read_to_string(path).map_err(|err| read_err(err.into()))?;
+        serde_yaml::from_str(&source).map_err(|err| read_err(err.into()))
+    }
+
+    /// Returns the answer with the given key.
+    pub fn get(&self, key: &str) -> Option<&Answer> {
+        self.values.get(key)
+    }
+
+    /// Returns the answer with the given key if it is a boolean.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(Answer::as_bool)
+    }
+
+    /// Returns the answer with the given key if it is a string.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Answer::as_str)
+    }
+
+    /// Insert an answer, returning the previous answer with the same key.
+    pub fn insert(&mut self, key: impl Into<String>, answer: Answer) -> Option<Answer> {
+        self.values.insert(key.into(), answer)
+    }
+
+    /// Add all answers from `other`, replacing answers with the same key.
+    pub fn extend(&mut self, other: Answers) {
+        self.values.extend(other.values);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Answer)> {
+        self.values
+            .iter()
+            .map(|(key, answer)| (key.as_str(), answer))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_scalars_as_answers() {
+        let answers: Answers =
+            serde_yaml::from_str("docker: true\nname: demo\nversion: 1\nratio: 1.5\n").unwrap();
+
+        assert_eq!(answers.get("docker"), Some(&Answer::Bool(true)));
+        assert_eq!(answers.get_str("name"), Some("demo"));
+        assert_eq!(answers.get_str("version"), Some("1"));
+        assert_eq!(answers.get_str("ratio"), Some("1.5"));
+    }
+}
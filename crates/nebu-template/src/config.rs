@@ -1,17 +1,377 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use miette::SourceSpan;
 use serde::{Deserialize, Serialize};
 
+use crate::answers::{Answer, Answers};
+use crate::content;
+use crate::error::{Error, ManifestError, ManifestProblem, Result};
+use crate::path;
+use crate::span::SpanIndex;
+
+/// Name of the manifest file at the root of a template.
+pub const MANIFEST_FILE: &str = ".nebu.yaml";
+
+/// The newest manifest schema version this crate understands.
+///
+/// Manifests without a `schema_version` are treated as version 1.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The template manifest, usually read from [`MANIFEST_FILE`].
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Config {
-    pub questions: Vec<String>,
-    pub actions: Vec<QuestionAction>,
+    /// Version of the manifest schema the template was written against.
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    /// Components that can be included in a generated project.
+    #[serde(default)]
+    pub components: Vec<Component>,
+    /// Questions asked when generating a project.
+    #[serde(default)]
+    pub questions: Vec<Question>,
+    /// Commands run in the project after it is generated.
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+impl Config {
+    /// Read and parse the manifest at the given path.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::parse(&read_manifest(path)?, path.display().to_string())
+    }
+
+    /// Read and parse the manifest in the root of the given template directory.
+    ///
+    /// Diagnostics refer to the manifest relative to the template root, as the
+    /// directory is usually a temporary checkout.
+    pub fn load_from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(MANIFEST_FILE);
+        Self::parse(&read_manifest(&path)?, MANIFEST_FILE)
+    }
+
+    /// Find a component by its id.
+    pub fn component(&self, id: &str) -> Option<&Component> {
+        self.components.iter().find(|component| component.id == id)
+    }
+
+    /// Find a component by its id or any of its aliases.
+    pub fn resolve_component(&self, name: &str) -> Result<&Component> {
+        self.component(name)
+            .or_else(|| {
+                self.components
+                    .iter()
+                    .find(|component| component.aliases.iter().any(|alias| alias == name))
+            })
+            .ok_or_else(|| Error::UnknownComponent {
+                name: name.to_string(),
+                available: self
+                    .components
+                    .iter()
+                    .map(|component| format!("`{}`", component.id))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+
+    /// Returns the questions that include the component with the given id.
+    pub fn component_questions(&self, id: &str) -> Vec<&Question> {
+        fn collect<'a>(questions: &'a [Question], id: &str, found: &mut Vec<&'a Question>) {
+            for question in questions {
+                if question.component.as_deref() == Some(id) {
+                    found.push(question);
+                }
+                collect(&question.subquestions, id, found);
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(&self.questions, id, &mut found);
+        found
+    }
+
+    /// Returns every question, including subquestions, in the order they are
+    /// declared.
+    pub fn all_questions(&self) -> Vec<&Question> {
+        fn collect<'a>(questions: &'a [Question], found: &mut Vec<&'a Question>) {
+            for question in questions {
+                found.push(question);
+                collect(&question.subquestions, found);
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(&self.questions, &mut found);
+        found
+    }
+
+    /// Returns the questions that were answered, excluding boolean questions
+    /// answered with `false` and everything below them.
+    pub fn active_questions(&self, answers: &Answers) -> Vec<&Question> {
+        fn collect<'a>(
+            questions: &'a [Question],
+            answers: &Answers,
+            found: &mut Vec<&'a Question>,
+        ) {
+            for question in questions {
+                if let None | Some(Answer::Bool(false)) = answers.get(&question.key()) {
+                    continue;
+                }
+                found.push(question);
+                collect(&question.subquestions, answers, found);
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(&self.questions, answers, &mut found);
+        found
+    }
+
+    /// Returns the ids of the components selected by the given answers.
+    ///
+    /// A component is selected when a boolean question referencing it, and
+    /// every question above it, is answered with `true`.
+    pub fn selected_components(&self, answers: &Answers) -> Vec<&str> {
+        self.active_questions(answers)
+            .into_iter()
+            .filter_map(|question| question.component.as_deref())
+            .collect()
+    }
+
+    /// Returns the hooks to run for the components selected by the answers.
+    pub fn active_hooks(&self, answers: &Answers) -> Vec<&Hook> {
+        let selected = self.selected_components(answers);
+        self.hooks
+            .iter()
+            .filter(|hook| {
+                hook.components.is_empty()
+                    || hook
+                        .components
+                        .iter()
+                        .any(|id| selected.contains(&id.as_str()))
+            })
+            .collect()
+    }
+
+    /// Parse and validate a manifest.
+    ///
+    /// The `name` is used to refer to the manifest in diagnostics, and is
+    /// usually the path the manifest was read from.
+    pub fn parse(source: &str, name: impl AsRef<str>) -> Result<Self> {
+        let spans = SpanIndex::new(source);
+
+        let mut problems = check_kinds(&spans);
+        if problems.is_empty() {
+            match serde_yaml::from_str::<Config>(source) {
+                Ok(config) => {
+                    problems = config.validate(&spans);
+                    if problems.is_empty() {
+                        return Ok(config);
+                    }
+                }
+                Err(err) => problems.push(ManifestProblem::from_yaml(&err, source)),
+            }
+        }
+
+        Err(ManifestError::new(name, source, problems).into())
+    }
+
+    /// Check the references and uniqueness constraints of the manifest.
+    fn validate(&self, spans: &SpanIndex) -> Vec<ManifestProblem> {
+        let mut problems = Vec::new();
+
+        if self.schema_version == 0 || self.schema_version > SCHEMA_VERSION {
+            problems.push(ManifestProblem::UnsupportedSchemaVersion {
+                found: self.schema_version,
+                supported: SCHEMA_VERSION,
+                span: spans.span("schema_version"),
+            });
+        }
+
+        let mut ids: HashMap<&str, Option<SourceSpan>> = HashMap::new();
+        let mut aliases: HashMap<&str, Option<SourceSpan>> = HashMap::new();
+        for (index, component) in self.components.iter().enumerate() {
+            let path = format!("components[{index}]");
+
+            let span = spans.span(&format!("{path}.id"));
+            if let Some(first) = ids.insert(&component.id, span) {
+                problems.push(ManifestProblem::DuplicateComponent {
+                    id: component.id.clone(),
+                    span,
+                    first,
+                });
+            }
+
+            for (alias_index, alias) in component.aliases.iter().enumerate() {
+                let span = spans.span(&format!("{path}.aliases[{alias_index}]"));
+                if let Some(first) = aliases.insert(alias, span) {
+                    problems.push(ManifestProblem::DuplicateAlias {
+                        alias: alias.clone(),
+                        span,
+                        first,
+                    });
+                }
+            }
+        }
+
+        let mut questions = HashMap::new();
+        for (index, question) in self.questions.iter().enumerate() {
+            question.validate(
+                &format!("questions[{index}]"),
+                self,
+                spans,
+                &mut questions,
+                &mut problems,
+            );
+        }
+
+        for (index, component) in self.components.iter().enumerate() {
+            for (target_index, target) in component.targets.iter().enumerate() {
+                let span = spans.span(&format!("components[{index}].targets[{target_index}].path"));
+                check_placeholders(target.path(), span, &questions, &mut problems);
+            }
+        }
+
+        for (index, hook) in self.hooks.iter().enumerate() {
+            let path = format!("hooks[{index}]");
+            if let Some(dir) = &hook.dir {
+                let span = spans.span(&format!("{path}.dir"));
+                check_placeholders(dir, span, &questions, &mut problems);
+            }
+
+            for (component_index, id) in hook.components.iter().enumerate() {
+                if self.component(id).is_none() {
+                    problems.push(ManifestProblem::UnknownComponent {
+                        id: id.clone(),
+                        span: spans.span(&format!("{path}.components[{component_index}]")),
+                    });
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+/// Check that a path only has placeholders referring to existing questions.
+fn check_placeholders(
+    template: &str,
+    span: Option<SourceSpan>,
+    questions: &HashMap<String, Option<SourceSpan>>,
+    problems: &mut Vec<ManifestProblem>,
+) {
+    match path::placeholders(template) {
+        Ok(keys) => problems.extend(
+            keys.into_iter()
+                .filter(|key| !questions.contains_key(*key))
+                .map(|key| ManifestProblem::UnknownPlaceholder {
+                    key: key.to_string(),
+                    span,
+                }),
+        ),
+        Err(err) => problems.push(ManifestProblem::InvalidPath {
+            reason: match err {
+                Error::InvalidPlaceholder { reason, .. } => reason,
+                err => err.to_string(),
+            },
+            span,
+        }),
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|source| Error::ReadManifest {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        Self::parse(source, MANIFEST_FILE)
+    }
+}
+
+/// Valid `kind` values, keyed by the name of the list holding the node.
+const KINDS: &[(&str, &str, &[&str])] = &[
+    ("components", "component", &["folder", "file"]),
+    ("targets", "target", &["folder", "file"]),
+    ("questions", "question", &["string", "boolean", "select"]),
+    ("subquestions", "question", &["string", "boolean", "select"]),
+    (
+        "actions",
+        "action",
+        &[
+            "replace_content",
+            "include_folder",
+            "rename_folder",
+            "rename_file",
+        ],
+    ),
+];
+
+/// Check every `kind` in the manifest before deserializing.
+///
+/// The deserializer reports unknown variants at the start of the enclosing
+/// mapping, so these are checked up front to point at the offending value.
+fn check_kinds(spans: &SpanIndex) -> Vec<ManifestProblem> {
+    let mut problems: Vec<_> = spans
+        .iter()
+        .filter_map(|(path, node)| {
+            let parent = path.strip_suffix(".kind")?;
+            let list = parent.strip_suffix(']')?.rsplit_once('[')?.0;
+            let list = list.rsplit('.').next()?;
+            let (_, context, expected) = KINDS.iter().find(|(name, ..)| *name == list)?;
+
+            let found = node.scalar.as_deref()?;
+            if expected.contains(&found) {
+                return None;
+            }
+
+            Some(ManifestProblem::UnknownKind {
+                context,
+                found: found.to_string(),
+                expected: expected
+                    .iter()
+                    .map(|kind| format!("`{kind}`"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                span: Some(node.value),
+            })
+        })
+        .collect();
+
+    problems.sort_by_key(|problem| match problem {
+        ManifestProblem::UnknownKind { span, .. } => span.map(|span| span.offset()),
+        _ => None,
+    });
+    problems
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Component {
     pub id: String,
     pub description: String,
+    pub kind: ComponentKind,
+    #[serde(default)]
     pub aliases: Vec<String>,
-    pub target: ComponentTarget,
+    #[serde(default)]
+    pub targets: Vec<ComponentTarget>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentKind {
+    Folder,
+    File,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -22,36 +382,270 @@ pub enum ComponentTarget {
     File { path: String },
 }
 
+impl ComponentTarget {
+    /// Path of the target relative to the template root.
+    pub fn path(&self) -> &str {
+        match self {
+            ComponentTarget::Folder { path } | ComponentTarget::File { path } => path,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Question {
+    /// Identifier used to refer to the answer of this question.
+    #[serde(default)]
+    pub id: Option<String>,
     pub prompt: String,
+    #[serde(flatten)]
     pub kind: QuestionKind,
+    /// The component that is included when this question is answered with `true`.
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub actions: Vec<QuestionAction>,
+    #[serde(default)]
     pub subquestions: Vec<Question>,
 }
 
+impl Question {
+    /// The key under which the answer to this question is stored.
+    ///
+    /// This is the `id` of the question, or the prompt in snake case if no id
+    /// is given.
+    pub fn key(&self) -> String {
+        match &self.id {
+            Some(id) => id.clone(),
+            None => self
+                .prompt
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+                .join("_"),
+        }
+    }
+
+    fn validate(
+        &self,
+        path: &str,
+        config: &Config,
+        spans: &SpanIndex,
+        ids: &mut HashMap<String, Option<SourceSpan>>,
+        problems: &mut Vec<ManifestProblem>,
+    ) {
+        let span = spans
+            .span(&format!("{path}.id"))
+            .or_else(|| spans.span(&format!("{path}.prompt")));
+        if let Some(first) = ids.insert(self.key(), span) {
+            problems.push(ManifestProblem::DuplicateQuestion {
+                id: self.key(),
+                span,
+                first,
+            });
+        }
+
+        if let Some(component) = &self.component
+            && config.component(component).is_none()
+        {
+            problems.push(ManifestProblem::UnknownComponent {
+                id: component.clone(),
+                span: spans.span(&format!("{path}.component")),
+            });
+        }
+
+        if let QuestionKind::Select {
+            options,
+            default: Some(default),
+        } = &self.kind
+            && !options.contains(default)
+        {
+            problems.push(ManifestProblem::InvalidDefault {
+                default: default.clone(),
+                span: spans.span(&format!("{path}.default")),
+            });
+        }
+
+        for (index, action) in self.actions.iter().enumerate() {
+            let glob = match action {
+                QuestionAction::ReplaceContent { glob }
+                | QuestionAction::IncludeFolder { glob } => glob.as_deref(),
+                QuestionAction::RenameFolder { glob, .. }
+                | QuestionAction::RenameFile { glob, .. } => Some(glob.as_str()),
+            };
+            if let Some(Err(Error::InvalidGlob { glob, source })) = glob.map(content::compile_glob)
+            {
+                problems.push(ManifestProblem::InvalidGlob {
+                    glob,
+                    reason: source.kind().to_string(),
+                    span: spans.span(&format!("{path}.actions[{index}].glob")),
+                });
+            }
+        }
+
+        for (index, question) in self.subquestions.iter().enumerate() {
+            question.validate(
+                &format!("{path}.subquestions[{index}]"),
+                config,
+                spans,
+                ids,
+                problems,
+            );
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 #[serde(tag = "kind")]
 pub enum QuestionKind {
-    #[serde(rename = "bool")]
-    Bool { default: bool, component: String },
-    #[serde(rename = "input")]
-    Input { default: String, component: String },
+    /// A free-form text answer.
+    String { default: Option<String> },
+    /// A yes or no answer.
+    Boolean { default: Option<bool> },
+    /// A choice between a fixed set of options.
+    Select {
+        options: Vec<String>,
+        default: Option<String>,
+    },
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "kind")]
 pub enum QuestionAction {
     /// Replace content from files within the given glob pattern.
-    #[serde(rename = "replace_content")]
-    ReplaceContent { glob: String },
+    ///
+    /// Applies to every file in the template if no glob is given.
+    ReplaceContent { glob: Option<String> },
     /// Include the folder from the given path.
-    #[serde(rename = "include_folder")]
-    IncludeFolder { glob: String },
+    ///
+    /// Includes the targets of the question's component if no glob is given.
+    IncludeFolder { glob: Option<String> },
     /// Rename a folder.
-    #[serde(rename = "rename_folder")]
     RenameFolder { glob: String, name: String },
     /// Rename a file.
-    #[serde(rename = "rename_file")]
     RenameFile { glob: String, name: String },
 }
+
+/// A command run in the project after it is generated.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Hook {
+    /// Name shown when running the hook.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Shell command to run.
+    pub run: String,
+    /// Directory to run the command in, relative to the project root.
+    ///
+    /// May contain placeholders, and defaults to the project root.
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Only run the hook if any of these components is selected.
+    ///
+    /// The hook always runs if no components are given.
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+impl Hook {
+    /// Name shown when running the hook, which is the command if no name is given.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.run)
+    }
+
+    /// Directory to run the command in, relative to the project root.
+    pub fn dir(&self, answers: &Answers) -> Result<Option<PathBuf>> {
+        self.dir
+            .as_deref()
+            .map(|dir| path::render_path(dir, answers))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_error(source: &str) -> ManifestError {
+        match Config::parse(source, "manifest") {
+            Err(Error::Manifest(err)) => err,
+            other => panic!("expected an invalid manifest, got {other:?}"),
+        }
+    }
+
+    fn offset(span: Option<SourceSpan>) -> usize {
+        span.expect("problem has a span").offset()
+    }
+
+    #[test]
+    fn labels_unknown_component_of_question() {
+        let source = r#"
+questions:
+  - id: 'with_web'
+    prompt: 'Web app?'
+    kind: 'boolean'
+    component: 'web'
+"#;
+
+        match manifest_error(source).problems() {
+            [ManifestProblem::UnknownComponent { id, span }] => {
+                assert_eq!(id, "web");
+                assert_eq!(offset(*span), source.find("'web'").unwrap());
+            }
+            problems => panic!("unexpected problems {problems:?}"),
+        }
+    }
+
+    #[test]
+    fn labels_duplicate_alias_and_first_use() {
+        let source = r#"
+components:
+  - id: 'api'
+    description: 'API'
+    kind: 'folder'
+    aliases:
+      - 'backend'
+  - id: 'worker'
+    description: 'Worker'
+    kind: 'folder'
+    aliases:
+      - 'backend'
+"#;
+
+        match manifest_error(source).problems() {
+            [ManifestProblem::DuplicateAlias { alias, span, first }] => {
+                assert_eq!(alias, "backend");
+                assert_eq!(offset(*first), source.find("'backend'").unwrap());
+                assert_eq!(offset(*span), source.rfind("'backend'").unwrap());
+            }
+            problems => panic!("unexpected problems {problems:?}"),
+        }
+    }
+
+    #[test]
+    fn labels_invalid_question_kind() {
+        let source = r#"
+questions:
+  - id: 'project_name'
+    prompt: 'Name?'
+    kind: 'text'
+"#;
+
+        match manifest_error(source).problems() {
+            [
+                ManifestProblem::UnknownKind {
+                    context,
+                    found,
+                    span,
+                    ..
+                },
+            ] => {
+                assert_eq!(*context, "question");
+                assert_eq!(found, "text");
+                assert_eq!(offset(*span), source.find("'text'").unwrap());
+            }
+            problems => panic!("unexpected problems {problems:?}"),
+        }
+    }
+}
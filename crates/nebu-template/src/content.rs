@@ -0,0 +1,454 @@
+//! Rendering of answers into the contents of template files.
+//!
+//! The syntax is a small subset of Jinja:
+//!
+//! - `{{ key }}` is replaced by the answer to the question `key`.
+//! - `{{ key | filter }}` applies filters to the answer, which are one of
+//!   `snake_case`, `kebab_case`, `pascal_case`, `camel_case`, `upper` and
+//!   `lower`. Filters can be chained.
+//! - `{% if key %}`, `{% if not key %}`, `{% else %}` and `{% endif %}` include
+//!   a block only when the answer is `true` or a non-empty string. Questions
+//!   that were never asked count as `false`.
+//! - `\{{` and `\{%` produce a literal `{{` or `{%`.
+//!
+//! Only expressions and tags on the questions of the manifest are rendered.
+//! Everything else, such as `{{ color: 'red' }}`, `${{ github.ref }}` or
+//! `{% for item in items %}`, is left as it is, so files of other template
+//! languages can be part of a template. The `else` and `endif` tags belonging
+//! to such an `if` tag are kept as well.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use heck::{ToKebabCase, ToLowerCamelCase, ToPascalCase, ToSnakeCase};
+use miette::SourceSpan;
+
+use crate::answers::{Answer, Answers};
+use crate::config::{Config, QuestionAction};
+use crate::error::{Error, RenderError, Result};
+
+/// Glob used by `replace_content` actions without an explicit glob.
+const ALL_FILES: &str = "**/*";
+
+/// Filters that can be applied to answers.
+const FILTERS: [&str; 6] = [
+    "snake_case",
+    "kebab_case",
+    "pascal_case",
+    "camel_case",
+    "upper",
+    "lower",
+];
+
+/// Matches the template files whose contents should be rendered.
+///
+/// These are the files matched by the `replace_content` actions of the
+/// questions that were answered, excluding boolean questions answered with
+/// `false`.
+pub struct ContentMatcher {
+    globs: GlobSet,
+}
+
+impl ContentMatcher {
+    pub fn new(config: &Config, answers: &Answers) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for question in config.active_questions(answers) {
+            for action in &question.actions {
+                if let QuestionAction::ReplaceContent { glob } = action {
+                    builder.add(compile_glob(glob.as_deref().unwrap_or(ALL_FILES))?);
+                }
+            }
+        }
+
+        let globs = builder.build().map_err(|source| Error::InvalidGlob {
+            glob: ALL_FILES.to_string(),
+            source,
+        })?;
+        Ok(ContentMatcher { globs })
+    }
+
+    /// Returns whether the file at the given path, relative to the template
+    /// root, should be rendered.
+    pub fn matches(&self, path: impl AsRef<Path>) -> bool {
+        self.globs.is_match(path)
+    }
+}
+
+/// Compile a glob from the template manifest.
+pub(crate) fn compile_glob(glob: &str) -> Result<Glob> {
+    Glob::new(glob).map_err(|source| Error::InvalidGlob {
+        glob: glob.to_string(),
+        source,
+    })
+}
+
+/// Render the answers into the contents of a template file.
+///
+/// Only expressions and tags that refer to the questions of `config` are
+/// rendered. The `name` is used to refer to the file in diagnostics.
+pub fn render_content(
+    name: &str,
+    source: &str,
+    config: &Config,
+    answers: &Answers,
+) -> Result<String> {
+    let error = |message: String, span: SourceSpan| RenderError::new(name, source, message, span);
+
+    let keys = config
+        .all_questions()
+        .iter()
+        .map(|question| question.key())
+        .collect();
+    let nodes = Parser::new(source, &keys)
+        .parse()
+        .map_err(|(message, span)| error(message, span))?;
+
+    let mut output = String::with_capacity(source.len());
+    render(&nodes, answers, &mut output).map_err(|(message, span)| error(message, span))?;
+    Ok(output)
+}
+
+type ParseResult<T> = std::result::Result<T, (String, SourceSpan)>;
+
+enum Node<'a> {
+    Text(&'a str),
+    Variable {
+        key: &'a str,
+        filters: Vec<&'a str>,
+        span: SourceSpan,
+    },
+    If {
+        key: &'a str,
+        negate: bool,
+        then: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+}
+
+enum Tag<'a> {
+    If {
+        key: &'a str,
+        negate: bool,
+    },
+    Else,
+    EndIf,
+    /// An `if` tag of another template language, such as `{% if a.b %}`.
+    ForeignIf,
+    /// Any other tag, which is left as it is.
+    Unknown,
+}
+
+/// Kind of an `if` block the parser is in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Block {
+    /// A block on one of the questions.
+    Known,
+    /// A block of another template language, whose `else` and `endif` tags
+    /// are left as they are.
+    Foreign,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+    /// Keys of the questions that can be referred to.
+    keys: &'a HashSet<String>,
+    /// The `if` blocks the parser is in, innermost last.
+    blocks: Vec<Block>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str, keys: &'a HashSet<String>) -> Self {
+        Parser {
+            source,
+            offset: 0,
+            keys,
+            blocks: Vec::new(),
+        }
+    }
+
+    fn parse(mut self) -> ParseResult<Vec<Node<'a>>> {
+        let (nodes, _) = self.parse_block()?;
+        Ok(nodes)
+    }
+
+    /// Parse nodes until the end of the source, or the `else`/`endif` tag of
+    /// the innermost known block.
+    #[allow(clippy::type_complexity)]
+    fn parse_block(&mut self) -> ParseResult<(Vec<Node<'a>>, Option<(Tag<'a>, SourceSpan)>)> {
+        let mut nodes = Vec::new();
+
+        loop {
+            let rest = &self.source[self.offset..];
+            let Some(index) = rest.find(['{', '\\']) else {
+                nodes.push(Node::Text(rest));
+                self.offset = self.source.len();
+                return Ok((nodes, None));
+            };
+
+            let start = self.offset + index;
+            let tail = &self.source[start..];
+            nodes.push(Node::Text(&rest[..index]));
+
+            if tail.starts_with("\\{{") || tail.starts_with("\\{%") {
+                nodes.push(Node::Text(&tail[1..3]));
+                self.offset = start + 3;
+            } else if tail.starts_with("{{") {
+                let Some(end) = tail.find("}}") else {
+                    nodes.push(Node::Text(&tail[..2]));
+                    self.offset = start + 2;
+                    continue;
+                };
+                let span = SourceSpan::from((start, end + 2));
+                self.offset = start + end + 2;
+                match self.parse_variable(&tail[2..end], span) {
+                    Some(node) => nodes.push(node),
+                    None => nodes.push(Node::Text(&tail[..end + 2])),
+                }
+            } else if tail.starts_with("{%")
+                && let Some(end) = tail.find("%}")
+            {
+                let span = SourceSpan::from((start, end + 2));
+                let text = &tail[..end + 2];
+                self.offset = start + end + 2;
+
+                match self.parse_tag(&tail[2..end]) {
+                    Tag::If { key, negate } => {
+                        self.blocks.push(Block::Known);
+                        let (then, end) = self.parse_block()?;
+                        let (otherwise, end) = match end {
+                            Some((Tag::Else, _)) => self.parse_block()?,
+                            end => (Vec::new(), end),
+                        };
+                        self.blocks.pop();
+                        match end {
+                            Some((Tag::EndIf, _)) => {}
+                            Some((_, span)) => {
+                                return Err(("expected `{% endif %}`".into(), span));
+                            }
+                            None => return Err(("unclosed `if` block".into(), span)),
+                        }
+                        nodes.push(Node::If {
+                            key,
+                            negate,
+                            then,
+                            otherwise,
+                        });
+                    }
+                    Tag::ForeignIf => {
+                        self.blocks.push(Block::Foreign);
+                        nodes.push(Node::Text(text));
+                    }
+                    Tag::EndIf if self.blocks.last() == Some(&Block::Foreign) => {
+                        self.blocks.pop();
+                        nodes.push(Node::Text(text));
+                    }
+                    tag @ (Tag::Else | Tag::EndIf) if self.blocks.last() == Some(&Block::Known) => {
+                        return Ok((nodes, Some((tag, span))));
+                    }
+                    _ => nodes.push(Node::Text(text)),
+                }
+            } else {
+                nodes.push(Node::Text(&tail[..1]));
+                self.offset = start + 1;
+            }
+        }
+    }
+
+    /// Parse `key | filter | ...`, returning `None` if it is not an expression
+    /// on one of the questions.
+    fn parse_variable(&self, inner: &'a str, span: SourceSpan) -> Option<Node<'a>> {
+        let mut parts = inner.split('|');
+        let key = parts.next()?.trim();
+        if !self.keys.contains(key) {
+            return None;
+        }
+
+        let filters = parts
+            .map(|part| Some(part.trim()).filter(|filter| FILTERS.contains(filter)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Node::Variable { key, filters, span })
+    }
+
+    fn parse_tag(&self, inner: &'a str) -> Tag<'a> {
+        let words: Vec<&str> = inner.split_whitespace().collect();
+        match words.as_slice() {
+            ["if", "not", key] if self.keys.contains(*key) => Tag::If { key, negate: true },
+            ["if", key] if self.keys.contains(*key) => Tag::If { key, negate: false },
+            ["if", ..] => Tag::ForeignIf,
+            ["else"] => Tag::Else,
+            ["endif"] => Tag::EndIf,
+            _ => Tag::Unknown,
+        }
+    }
+}
+
+fn render(nodes: &[Node<'_>], answers: &Answers, output: &mut String) -> ParseResult<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable { key, filters, span } => {
+                let answer = answers
+                    .get(key)
+                    .ok_or_else(|| (format!("no answer for `{key}`"), *span))?;
+
+                let value = filters.iter().fold(answer.to_string(), |value, filter| {
+                    apply_filter(filter, &value)
+                });
+                output.push_str(&value);
+            }
+            Node::If {
+                key,
+                negate,
+                then,
+                otherwise,
+            } => {
+                let truthy = match answers.get(key) {
+                    Some(Answer::Bool(value)) => *value,
+                    Some(Answer::String(value)) => !value.is_empty(),
+                    None => false,
+                };
+                let block = if truthy != *negate { then } else { otherwise };
+                render(block, answers, output)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_filter(filter: &str, value: &str) -> String {
+    match filter {
+        "snake_case" => value.to_snake_case(),
+        "kebab_case" => value.to_kebab_case(),
+        "pascal_case" => value.to_pascal_case(),
+        "camel_case" => value.to_lower_camel_case(),
+        "upper" => value.to_uppercase(),
+        "lower" => value.to_lowercase(),
+        _ => unreachable!("filters are checked when parsing"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+questions:
+  - id: 'name'
+    prompt: 'Name?'
+    kind: 'string'
+  - id: 'docker'
+    prompt: 'Docker?'
+    kind: 'boolean'
+  - id: 'unasked'
+    prompt: 'Never asked?'
+    kind: 'boolean'
+"#;
+
+    fn render(source: &str) -> Result<String> {
+        let config = Config::parse(MANIFEST, "manifest").unwrap();
+        let mut answers = Answers::new();
+        answers.insert("name", Answer::String("My App".to_string()));
+        answers.insert("docker", Answer::Bool(true));
+        render_content("file", source, &config, &answers)
+    }
+
+    #[test]
+    fn renders_variables_with_filters() {
+        assert_eq!(
+            render("{{ name }} {{name|snake_case}} {{ name | kebab_case | upper }}").unwrap(),
+            "My App my_app MY-APP"
+        );
+    }
+
+    #[test]
+    fn renders_if_blocks() {
+        assert_eq!(
+            render("{% if docker %}a{% else %}b{% endif %}").unwrap(),
+            "a"
+        );
+        assert_eq!(
+            render("{% if not docker %}a{% else %}b{% endif %}").unwrap(),
+            "b"
+        );
+        assert_eq!(render("{% if unasked %}a{% endif %}").unwrap(), "");
+        assert_eq!(
+            render("{% if docker %}{% if name %}{{ name }}{% endif %}{% endif %}").unwrap(),
+            "My App"
+        );
+    }
+
+    #[test]
+    fn escapes_produce_literal_braces() {
+        assert_eq!(
+            render("\\{{ name }} \\{% if docker %}").unwrap(),
+            "{{ name }} {% if docker %}"
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_expressions_unchanged() {
+        for source in [
+            "{{ other }}",
+            "${{ github.ref }}",
+            "{{ .Values.image }}",
+            "{{ name | title }}",
+            "{{ color: 'red' }}",
+            "{{ unclosed",
+        ] {
+            assert_eq!(render(source).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn leaves_unknown_tags_unchanged() {
+        for source in [
+            "{% for item in items %}{{ item }}{% endfor %}",
+            "{% if user.admin %}a{% elif b %}b{% else %}c{% endif %}",
+            "{% if other %}a{% endif %}",
+            "{% endif %}{% else %}",
+            "{% raw",
+        ] {
+            assert_eq!(render(source).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn foreign_if_blocks_do_not_close_known_ones() {
+        assert_eq!(
+            render("{% if docker %}{% if a.b %}x{% else %}y{% endif %}{% endif %}").unwrap(),
+            "{% if a.b %}x{% else %}y{% endif %}"
+        );
+        assert_eq!(
+            render("{% if not docker %}{% if a.b %}x{% endif %}{% endif %}").unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn known_and_foreign_blocks_close_their_own_endif() {
+        assert_eq!(
+            render("{% if a.b %}P{% if unasked %}Q{% endif %}R{% endif %}S").unwrap(),
+            "{% if a.b %}PR{% endif %}S"
+        );
+        assert_eq!(
+            render("{% if a.b %}P{% if docker %}Q{% else %}q{% endif %}R{% endif %}S").unwrap(),
+            "{% if a.b %}PQR{% endif %}S"
+        );
+        assert_eq!(
+            render("{% if docker %}P{% if a.b %}Q{% endif %}R{% endif %}S").unwrap(),
+            "P{% if a.b %}Q{% endif %}RS"
+        );
+        assert_eq!(
+            render("{% if unasked %}P{% if a.b %}Q{% endif %}R{% endif %}S").unwrap(),
+            "S"
+        );
+    }
+
+    #[test]
+    fn rejects_unclosed_if_blocks() {
+        let err = render("{% if docker %}a").unwrap_err();
+        assert!(err.to_string().contains("unclosed `if` block"), "{err}");
+    }
+}
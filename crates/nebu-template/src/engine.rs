@@ -0,0 +1,241 @@
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, Input, Select};
+
+use crate::answers::{Answer, Answers};
+use crate::config::{Question, QuestionKind};
+use crate::error::{Error, Result};
+
+/// Something that can answer questions, usually by asking the user.
+pub trait Prompter {
+    /// Ask a yes or no question.
+    fn confirm(&mut self, question: &Question, default: Option<bool>) -> Result<bool>;
+    /// Ask for a free-form text answer.
+    fn input(&mut self, question: &Question, default: Option<&str>) -> Result<String>;
+    /// Ask to pick one of the given options.
+    fn select(
+        &mut self,
+        question: &Question,
+        options: &[String],
+        default: Option<&str>,
+    ) -> Result<String>;
+}
+
+impl<P: Prompter + ?Sized> Prompter for &mut P {
+    fn confirm(&mut self, question: &Question, default: Option<bool>) -> Result<bool> {
+        (**self).confirm(question, default)
+    }
+
+    fn input(&mut self, question: &Question, default: Option<&str>) -> Result<String> {
+        (**self).input(question, default)
+    }
+
+    fn select(
+        &mut self,
+        question: &Question,
+        options: &[String],
+        default: Option<&str>,
+    ) -> Result<String> {
+        (**self).select(question, options, default)
+    }
+}
+
+impl<P: Prompter + ?Sized> Prompter for Box<P> {
+    fn confirm(&mut self, question: &Question, default: Option<bool>) -> Result<bool> {
+        (**self).confirm(question, default)
+    }
+
+    fn input(&mut self, question: &Question, default: Option<&str>) -> Result<String> {
+        (**self).input(question, default)
+    }
+
+    fn select(
+        &mut self,
+        question: &Question,
+        options: &[String],
+        default: Option<&str>,
+    ) -> Result<String> {
+        (**self).select(question, options, default)
+    }
+}
+
+/// Asks questions interactively in the terminal.
+#[derive(Default)]
+pub struct DialoguerPrompter {
+    theme: ColorfulTheme,
+}
+
+impl Prompter for DialoguerPrompter {
+    fn confirm(&mut self, question: &Question, default: Option<bool>) -> Result<bool> {
+        let mut prompt = Confirm::with_theme(&self.theme).with_prompt(&question.prompt);
+        if let Some(default) = default {
+            prompt = prompt.default(default);
+        }
+        Ok(prompt.interact()?)
+    }
+
+    fn input(&mut self, question: &Question, default: Option<&str>) -> Result<String> {
+        let mut prompt = Input::<String>::with_theme(&self.theme).with_prompt(&question.prompt);
+        if let Some(default) = default {
+            prompt = prompt.default(default.to_string());
+        }
+        Ok(prompt.interact_text()?)
+    }
+
+    fn select(
+        &mut self,
+        question: &Question,
+        options: &[String],
+        default: Option<&str>,
+    ) -> Result<String> {
+        let default = default
+            .and_then(|default| options.iter().position(|option| option == default))
+            .unwrap_or(0);
+        let index = Select::with_theme(&self.theme)
+            .with_prompt(&question.prompt)
+            .items(options)
+            .default(default)
+            .interact()?;
+        Ok(options[index].clone())
+    }
+}
+
+/// Answers questions from a set of preset answers.
+///
+/// Questions without a preset answer fall back to their default. Questions
+/// without either are passed on to the fallback prompter, or fail with
+/// [`Error::MissingAnswer`] if there is none.
+pub struct PresetPrompter<P> {
+    presets: Answers,
+    fallback: Option<P>,
+}
+
+impl<P: Prompter> PresetPrompter<P> {
+    pub fn new(presets: Answers, fallback: Option<P>) -> Self {
+        PresetPrompter { presets, fallback }
+    }
+
+    fn preset(&self, question: &Question) -> Option<&Answer> {
+        self.presets.get(&question.key())
+    }
+
+    fn fallback(&mut self, question: &Question) -> Result<&mut P> {
+        self.fallback.as_mut().ok_or_else(|| Error::MissingAnswer {
+            key: question.key(),
+            prompt: question.prompt.clone(),
+        })
+    }
+}
+
+impl<P: Prompter> Prompter for PresetPrompter<P> {
+    fn confirm(&mut self, question: &Question, default: Option<bool>) -> Result<bool> {
+        if let Some(answer) = self.preset(question) {
+            return match answer {
+                Answer::Bool(value) => Ok(*value),
+                Answer::String(value) => match value.to_lowercase().as_str() {
+                    "true" | "yes" | "y" | "on" | "1" => Ok(true),
+                    "false" | "no" | "n" | "off" | "0" => Ok(false),
+                    _ => Err(invalid_answer(question, answer, "a boolean")),
+                },
+            };
+        }
+
+        match default {
+            Some(default) => Ok(default),
+            None => self.fallback(question)?.confirm(question, None),
+        }
+    }
+
+    fn input(&mut self, question: &Question, default: Option<&str>) -> Result<String> {
+        if let Some(answer) = self.preset(question) {
+            return Ok(answer.to_string());
+        }
+
+        match default {
+            Some(default) => Ok(default.to_string()),
+            None => self.fallback(question)?.input(question, None),
+        }
+    }
+
+    fn select(
+        &mut self,
+        question: &Question,
+        options: &[String],
+        default: Option<&str>,
+    ) -> Result<String> {
+        if let Some(answer) = self.preset(question) {
+            let value = answer.to_string();
+            if !options.contains(&value) {
+                let expected = format!("one of {}", options.join(", "));
+                return Err(invalid_answer(question, answer, &expected));
+            }
+            return Ok(value);
+        }
+
+        match default {
+            Some(default) => Ok(default.to_string()),
+            None => self.fallback(question)?.select(question, options, None),
+        }
+    }
+}
+
+fn invalid_answer(question: &Question, answer: &Answer, expected: &str) -> Error {
+    Error::InvalidAnswer {
+        key: question.key(),
+        value: answer.to_string(),
+        expected: expected.to_string(),
+    }
+}
+
+/// Walks a question tree and collects the answers.
+///
+/// Subquestions of a boolean question are only asked when it is answered with
+/// `true`, while subquestions of other questions are always asked.
+pub struct QuestionEngine<P> {
+    prompter: P,
+    answers: Answers,
+}
+
+impl<P: Prompter> QuestionEngine<P> {
+    pub fn new(prompter: P) -> Self {
+        QuestionEngine {
+            prompter,
+            answers: Answers::new(),
+        }
+    }
+
+    /// Ask the given questions and their subquestions in order.
+    pub fn ask(mut self, questions: &[Question]) -> Result<Answers> {
+        self.ask_all(questions)?;
+        Ok(self.answers)
+    }
+
+    fn ask_all(&mut self, questions: &[Question]) -> Result<()> {
+        for question in questions {
+            let answer = self.ask_one(question)?;
+            tracing::debug!("answered `{}` with `{answer}`", question.key());
+
+            let descend = answer.as_bool().unwrap_or(true);
+            self.answers.insert(question.key(), answer);
+
+            if descend {
+                self.ask_all(&question.subquestions)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ask_one(&mut self, question: &Question) -> Result<Answer> {
+        let answer = match &question.kind {
+            QuestionKind::Boolean { default } => {
+                Answer::Bool(self.prompter.confirm(question, *default)?)
+            }
+            QuestionKind::String { default } => {
+                Answer::String(self.prompter.input(question, default.as_deref())?)
+            }
+            QuestionKind::Select { options, default } => {
+                Answer::String(self.prompter.select(question, options, default.as_deref())?)
+            }
+        };
+        Ok(answer)
+    }
+}
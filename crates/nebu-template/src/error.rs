@@ -0,0 +1,334 @@
+use std::path::PathBuf;
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Diagnostic, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    #[diagnostic(code(template::io_error))]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to read the template manifest at {}", path.display())]
+    #[diagnostic(
+        code(template::missing_manifest),
+        help("Make sure the template has a `.nebu.yaml` manifest in its root")
+    )]
+    ReadManifest {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Manifest(#[from] ManifestError),
+
+    #[error("failed to prompt for an answer")]
+    #[diagnostic(
+        code(template::prompt_error),
+        help("Make sure nebu is running in an interactive terminal")
+    )]
+    Prompt(#[from] dialoguer::Error),
+
+    #[error("the template has no component named `{name}`")]
+    #[diagnostic(
+        code(template::unknown_component),
+        help("Available components are {available}, or any of their aliases")
+    )]
+    UnknownComponent { name: String, available: String },
+
+    #[error("no answer for the placeholder `[{key}]` in `{path}`")]
+    #[diagnostic(
+        code(template::unknown_placeholder),
+        help("Placeholders must refer to the id of a question that has been answered")
+    )]
+    UnknownPlaceholder { key: String, path: String },
+
+    #[error("invalid placeholder in `{path}`: {reason}")]
+    #[diagnostic(code(template::invalid_placeholder))]
+    InvalidPlaceholder { path: String, reason: String },
+
+    #[error("the path `{path}` renders to `{rendered}`, which is outside of the project")]
+    #[diagnostic(
+        code(template::path_escapes_root),
+        help("Answers used in paths must not be empty, absolute or contain `..`")
+    )]
+    PathEscapesRoot { path: String, rendered: String },
+
+    #[error("invalid glob `{glob}` in the template manifest")]
+    #[diagnostic(code(template::invalid_glob))]
+    InvalidGlob {
+        glob: String,
+        #[source]
+        source: globset::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Render(#[from] RenderError),
+
+    #[error(
+        "both `{}` and `{}` would be written to `{}`",
+        first.display(),
+        second.display(),
+        target.display()
+    )]
+    #[diagnostic(
+        code(template::plan_conflict),
+        help("Make sure the rename actions of the template produce distinct paths")
+    )]
+    PlanConflict {
+        target: PathBuf,
+        first: PathBuf,
+        second: PathBuf,
+    },
+
+    #[error("no answer given for `{key}` ({prompt})")]
+    #[diagnostic(
+        code(template::missing_answer),
+        help("Provide an answer for `{key}`, or allow nebu to prompt for it")
+    )]
+    MissingAnswer { key: String, prompt: String },
+
+    #[error("invalid answer `{value}` for `{key}`, expected {expected}")]
+    #[diagnostic(code(template::invalid_answer))]
+    InvalidAnswer {
+        key: String,
+        value: String,
+        expected: String,
+    },
+
+    #[error("failed to read answers from {}", path.display())]
+    #[diagnostic(
+        code(template::invalid_answers),
+        help("The answers file must be a YAML mapping from question ids to answers")
+    )]
+    ReadAnswers {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("failed to read the project lockfile at {}", path.display())]
+    #[diagnostic(
+        code(template::invalid_lockfile),
+        help("Make sure the command runs in a project generated by `nebu project init`")
+    )]
+    ReadLockfile {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// A template manifest that could not be parsed or failed validation.
+///
+/// Every problem found in the manifest is reported as a related diagnostic
+/// pointing into the manifest source.
+#[derive(Error, Diagnostic, Debug)]
+#[error("the template manifest `{}` is invalid", src.name())]
+#[diagnostic(
+    code(template::invalid_manifest),
+    url("https://nebu.lerpz.com/docs/cli/errors#invalid_manifest")
+)]
+pub struct ManifestError {
+    #[source_code]
+    src: NamedSource<String>,
+    #[related]
+    problems: Vec<ManifestProblem>,
+}
+
+impl ManifestError {
+    pub(crate) fn new(
+        name: impl AsRef<str>,
+        source: impl Into<String>,
+        problems: Vec<ManifestProblem>,
+    ) -> Self {
+        ManifestError {
+            src: NamedSource::new(name, source.into()).with_language("yaml"),
+            problems,
+        }
+    }
+
+    /// The problems found in the manifest.
+    pub fn problems(&self) -> &[ManifestProblem] {
+        &self.problems
+    }
+}
+
+/// A single problem found in a template manifest.
+#[derive(Error, Diagnostic, Debug)]
+pub enum ManifestProblem {
+    #[error("{message}")]
+    #[diagnostic(code(template::manifest::syntax))]
+    Syntax {
+        message: String,
+        #[label("here")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("unsupported schema version {found}")]
+    #[diagnostic(
+        code(template::manifest::schema_version),
+        help("This version of nebu supports schema versions 1 to {supported}, try updating nebu")
+    )]
+    UnsupportedSchemaVersion {
+        found: u32,
+        supported: u32,
+        #[label("unsupported version")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("unknown {context} kind `{found}`")]
+    #[diagnostic(
+        code(template::manifest::unknown_kind),
+        help("Expected one of {expected}")
+    )]
+    UnknownKind {
+        context: &'static str,
+        found: String,
+        expected: String,
+        #[label("unknown kind")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("unknown component `{id}`")]
+    #[diagnostic(
+        code(template::manifest::unknown_component),
+        help("Declare the component under `components` or reference an existing component id")
+    )]
+    UnknownComponent {
+        id: String,
+        #[label("not declared in `components`")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("duplicate component id `{id}`")]
+    #[diagnostic(code(template::manifest::duplicate_component))]
+    DuplicateComponent {
+        id: String,
+        #[label("declared again here")]
+        span: Option<SourceSpan>,
+        #[label("first declared here")]
+        first: Option<SourceSpan>,
+    },
+
+    #[error("duplicate alias `{alias}`")]
+    #[diagnostic(
+        code(template::manifest::duplicate_alias),
+        help("Aliases must be unique across all components")
+    )]
+    DuplicateAlias {
+        alias: String,
+        #[label("used again here")]
+        span: Option<SourceSpan>,
+        #[label("first used here")]
+        first: Option<SourceSpan>,
+    },
+
+    #[error("duplicate question id `{id}`")]
+    #[diagnostic(code(template::manifest::duplicate_question))]
+    DuplicateQuestion {
+        id: String,
+        #[label("used again here")]
+        span: Option<SourceSpan>,
+        #[label("first used here")]
+        first: Option<SourceSpan>,
+    },
+
+    #[error("unknown placeholder `[{key}]`")]
+    #[diagnostic(
+        code(template::manifest::unknown_placeholder),
+        help("Placeholders must refer to the id of a question")
+    )]
+    UnknownPlaceholder {
+        key: String,
+        #[label("no question with this id")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("invalid target path, {reason}")]
+    #[diagnostic(code(template::manifest::invalid_path))]
+    InvalidPath {
+        reason: String,
+        #[label("invalid path")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("invalid glob `{glob}`, {reason}")]
+    #[diagnostic(code(template::manifest::invalid_glob))]
+    InvalidGlob {
+        glob: String,
+        reason: String,
+        #[label("invalid glob")]
+        span: Option<SourceSpan>,
+    },
+
+    #[error("default `{default}` is not one of the options")]
+    #[diagnostic(code(template::manifest::invalid_default))]
+    InvalidDefault {
+        default: String,
+        #[label("not listed in `options`")]
+        span: Option<SourceSpan>,
+    },
+}
+
+/// A template file whose contents could not be rendered.
+#[derive(Error, Diagnostic, Debug)]
+#[error("failed to render `{}`, {message}", src.name())]
+#[diagnostic(
+    code(template::render_error),
+    help("Use `\\{{{{` or `\\{{%` to write a literal `{{{{` or `{{%`")
+)]
+pub struct RenderError {
+    #[source_code]
+    src: NamedSource<String>,
+    message: String,
+    #[label("here")]
+    span: SourceSpan,
+}
+
+impl RenderError {
+    pub(crate) fn new(
+        name: impl AsRef<str>,
+        source: impl Into<String>,
+        message: String,
+        span: SourceSpan,
+    ) -> Self {
+        RenderError {
+            src: NamedSource::new(name, source.into()),
+            message,
+            span,
+        }
+    }
+}
+
+impl ManifestProblem {
+    /// Create a problem from a deserialization error, locating it in `source`.
+    pub(crate) fn from_yaml(err: &serde_yaml::Error, source: &str) -> Self {
+        let message = err.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+
+        let span = err.location().map(|location| {
+            let line_start: usize = source
+                .split_inclusive('\n')
+                .take(location.line().saturating_sub(1))
+                .map(str::len)
+                .sum();
+            let column: usize = source[line_start..]
+                .chars()
+                .take(location.column().saturating_sub(1))
+                .map(char::len_utf8)
+                .sum();
+            SourceSpan::from((line_start + column, 0))
+        });
+
+        ManifestProblem::Syntax { message, span }
+    }
+}
@@ -1,6 +1,20 @@
 //! The templating library responsible for rendering templates.
 
+pub use answers::*;
 pub use config::*;
+pub use content::{ContentMatcher, render_content};
+pub use engine::*;
+pub use error::{Error, ManifestError, ManifestProblem, RenderError, Result};
+pub use lock::*;
+pub use path::render_path;
+pub use plan::*;
 
+pub mod answers;
 pub mod config;
-
+pub mod content;
+pub mod engine;
+mod error;
+pub mod lock;
+pub mod path;
+pub mod plan;
+mod span;
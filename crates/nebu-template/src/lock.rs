@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::answers::Answers;
+use crate::error::{Error, Result};
+
+/// Name of the lockfile written to the root of a generated project.
+pub const LOCK_FILE: &str = ".nebu.lock";
+
+/// The newest lockfile version this crate understands.
+pub const LOCK_VERSION: u32 = 1;
+
+/// Records how a project was generated.
+///
+/// This allows components to be added to or upgraded in a project later on
+/// without asking the same questions again.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Lockfile {
+    /// Version of the lockfile format.
+    pub version: u32,
+    /// Ids of the components included in the project.
+    pub components: Vec<String>,
+    /// The template the project was generated from.
+    pub template: TemplateLock,
+    /// Answers given to the template questions.
+    pub answers: Answers,
+}
+
+/// The exact revision of a template used to generate a project.
+///
+/// Only git templates have a branch, remote and commit.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TemplateLock {
+    /// Kind of source the template was read from.
+    #[serde(default, skip_serializing_if = "TemplateKind::is_git")]
+    pub kind: TemplateKind,
+    /// URL of the template repository, or path of a local template.
+    pub url: String,
+    /// Branch of the template repository.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub branch: String,
+    /// Remote the branch was fetched from.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub remote: String,
+    /// Tag the template was pinned to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// OID of the commit the project was generated from.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub commit: String,
+}
+
+/// Kind of source a template was read from.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    /// A git repository.
+    #[default]
+    Git,
+    /// A directory on the local filesystem.
+    Directory,
+    /// A `.tar.gz` or `.zip` archive on the local filesystem.
+    Archive,
+}
+
+impl TemplateKind {
+    fn is_git(&self) -> bool {
+        *self == TemplateKind::Git
+    }
+}
+
+impl Lockfile {
+    pub fn new(template: TemplateLock, components: Vec<String>, answers: Answers) -> Self {
+        Lockfile {
+            version: LOCK_VERSION,
+            components,
+            template,
+            answers,
+        }
+    }
+
+    /// Read the lockfile in the root of the given project directory.
+    pub fn load_from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(LOCK_FILE);
+        let read_err = |source: Box<dyn std::error::Error + Send + Sync>| Error::ReadLockfile {
+            path: path.clone(),
+            source,
+        };
+
+        let source = std::fs::read_to_string(&path).map_err(|err| read_err(err.into()))?;
+        let lockfile: Lockfile = toml::from_str(&source).map_err(|err| read_err(err.into()))?;
+
+        if lockfile.version > LOCK_VERSION {
+            return Err(read_err(
+                format!(
+                    "unsupported lockfile version {}, this version of nebu supports up to {LOCK_VERSION}",
+                    lockfile.version
+                )
+                .into(),
+            ));
+        }
+
+        Ok(lockfile)
+    }
+
+    /// Write the lockfile to the root of the given project directory.
+    pub fn write_to_dir(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let contents = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(dir.as_ref().join(LOCK_FILE), contents)?;
+        Ok(())
+    }
+}
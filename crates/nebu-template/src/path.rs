@@ -0,0 +1,203 @@
+//! Rendering of placeholders in template paths.
+//!
+//! A path segment may contain placeholders in square brackets, which are
+//! replaced by the answer to the question with that key. For example, the
+//! path `packages/[frontend]` renders to `packages/web` when the question
+//! `frontend` is answered with `web`. A literal bracket is written by doubling
+//! it, such as `[[` or `]]`.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::answers::Answers;
+use crate::error::{Error, Result};
+
+/// Returns the placeholder keys used in a template path.
+pub fn placeholders(template: &str) -> Result<Vec<&str>> {
+    let mut keys = Vec::new();
+    parse(template, |part| {
+        if let Part::Placeholder(key) = part {
+            keys.push(key);
+        }
+    })?;
+    Ok(keys)
+}
+
+/// Render the placeholders in a template path against the answers.
+///
+/// The result is always relative, and fails with [`Error::PathEscapesRoot`]
+/// if it would point outside of the project root.
+pub fn render_path(template: &str, answers: &Answers) -> Result<PathBuf> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut missing = None;
+    parse(template, |part| match part {
+        Part::Literal(text) => rendered.push_str(text),
+        Part::Placeholder(key) => match answers.get(key) {
+            Some(answer) => rendered.push_str(&answer.to_string()),
+            None => missing = missing.or(Some(key)),
+        },
+    })?;
+
+    if let Some(key) = missing {
+        return Err(Error::UnknownPlaceholder {
+            key: key.to_string(),
+            path: template.to_string(),
+        });
+    }
+
+    let mut path = PathBuf::new();
+    for component in Path::new(&rendered).components() {
+        match component {
+            Component::Normal(segment) => path.push(segment),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(Error::PathEscapesRoot {
+                    path: template.to_string(),
+                    rendered,
+                });
+            }
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        return Err(Error::PathEscapesRoot {
+            path: template.to_string(),
+            rendered,
+        });
+    }
+
+    Ok(path)
+}
+
+enum Part<'a> {
+    Literal(&'a str),
+    Placeholder(&'a str),
+}
+
+fn parse<'a>(template: &'a str, mut visit: impl FnMut(Part<'a>)) -> Result<()> {
+    let invalid = |reason: &str| Error::InvalidPlaceholder {
+        path: template.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let mut rest = template;
+    while let Some(index) = rest.find(['[', ']']) {
+        visit(Part::Literal(&rest[..index]));
+        let bracket = &rest[index..index + 1];
+        rest = &rest[index + 1..];
+
+        if let Some(after) = rest.strip_prefix(bracket) {
+            visit(Part::Literal(bracket));
+            rest = after;
+        } else if bracket == "]" {
+            return Err(invalid("unmatched `]`, use `]]` for a literal bracket"));
+        } else {
+            let end = rest
+                .find(']')
+                .ok_or_else(|| invalid("unclosed `[`, use `[[` for a literal bracket"))?;
+            let key = &rest[..end];
+            if key.is_empty() || key.contains(['[', '/', '\\']) {
+                return Err(invalid("placeholders must contain a question id"));
+            }
+            visit(Part::Placeholder(key));
+            rest = &rest[end + 1..];
+        }
+    }
+    visit(Part::Literal(rest));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::answers::Answer;
+
+    fn answers(pairs: &[(&str, &str)]) -> Answers {
+        let mut answers = Answers::new();
+        for (key, value) in pairs {
+            answers.insert(*key, Answer::String(value.to_string()));
+        }
+        answers
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let answers = answers(&[("frontend", "web")]);
+        let path = render_path("packages/[frontend]/src", &answers).unwrap();
+        assert_eq!(path, Path::new("packages/web/src"));
+    }
+
+    #[test]
+    fn doubled_brackets_are_literal() {
+        let answers = answers(&[("name", "web")]);
+        let path = render_path("[[x]]/[name]-[[]]", &answers).unwrap();
+        assert_eq!(path, Path::new("[x]/web-[]"));
+    }
+
+    #[test]
+    fn lists_placeholders() {
+        assert_eq!(placeholders("[a]/[[b]]/[c]").unwrap(), ["a", "c"]);
+    }
+
+    #[test]
+    fn rejects_invalid_placeholders() {
+        let answers = answers(&[("name", "web")]);
+        for template in ["[name", "name]", "[]", "[a/b]", "[a[b]"] {
+            assert!(
+                matches!(
+                    render_path(template, &answers),
+                    Err(Error::InvalidPlaceholder { .. })
+                ),
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        let err = render_path("[missing]", &Answers::new()).unwrap_err();
+        assert!(matches!(err, Error::UnknownPlaceholder { key, .. } if key == "missing"));
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_the_root() {
+        for (template, answer) in [
+            ("[name]", ".."),
+            ("[name]/x", "../.."),
+            ("src/[name]", "../../etc"),
+            ("[name]", "/etc"),
+            ("/etc/[name]", "x"),
+            ("../[name]", "x"),
+        ] {
+            let answers = answers(&[("name", answer)]);
+            assert!(
+                matches!(
+                    render_path(template, &answers),
+                    Err(Error::PathEscapesRoot { .. })
+                ),
+                "{template} with {answer}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_empty_paths() {
+        for answer in ["", ".", "./."] {
+            let answers = answers(&[("name", answer)]);
+            assert!(
+                matches!(
+                    render_path("[name]", &answers),
+                    Err(Error::PathEscapesRoot { .. })
+                ),
+                "{answer:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn skips_current_dir_segments() {
+        let answers = answers(&[("name", "./web")]);
+        let path = render_path("packages/[name]", &answers).unwrap();
+        assert_eq!(path, Path::new("packages/web"));
+    }
+}
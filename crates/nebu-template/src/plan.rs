@@ -0,0 +1,501 @@
+//! Planning and applying the file operations that generate a project.
+//!
+//! A [`Plan`] is computed from the template, the manifest and the answers
+//! before anything is written, so that rendering errors and conflicting
+//! operations are reported while the destination is still untouched.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobSet, GlobSetBuilder};
+use serde::Serialize;
+
+use crate::answers::Answers;
+use crate::config::{Config, MANIFEST_FILE, QuestionAction};
+use crate::content::{ContentMatcher, compile_glob, render_content};
+use crate::error::{Error, Result};
+use crate::path::render_path;
+
+/// What happens to a single template file.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    /// The file is copied as is.
+    Copy,
+    /// The file is copied as is to a different path.
+    Rename,
+    /// The file is written with its contents rendered.
+    Rewrite,
+    /// The file is not part of the project.
+    Skip,
+    /// The file already exists in the project and is left as is.
+    Exists,
+}
+
+/// A planned operation on a single template file.
+#[derive(Serialize, Debug, Clone)]
+pub struct Operation {
+    pub kind: OperationKind,
+    /// Path of the file in the template.
+    pub source: PathBuf,
+    /// Path of the file in the project, or the template path if skipped.
+    pub target: PathBuf,
+    /// Rendered contents of the file, if it is rewritten.
+    #[serde(skip)]
+    pub contents: Option<String>,
+}
+
+impl Operation {
+    /// Size in bytes of the file written by this operation.
+    pub fn size(&self, template: &Path) -> Result<u64> {
+        match &self.contents {
+            Some(contents) => Ok(contents.len() as u64),
+            None => Ok(std::fs::metadata(template.join(&self.source))?.len()),
+        }
+    }
+}
+
+/// An ordered list of file operations that generate a project.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Plan {
+    pub operations: Vec<Operation>,
+}
+
+impl Plan {
+    /// Plan the generation of a project from the template at `root`.
+    pub fn new(root: &Path, config: &Config, answers: &Answers) -> Result<Self> {
+        Planner::new(config, answers)?.plan(root, |_| true)
+    }
+
+    /// Plan only the files of a single component of the template at `root`.
+    pub fn for_component(
+        root: &Path,
+        config: &Config,
+        component: &str,
+        answers: &Answers,
+    ) -> Result<Self> {
+        let targets: Vec<&Path> = config
+            .component(component)
+            .map(|component| {
+                component
+                    .targets
+                    .iter()
+                    .map(|t| Path::new(t.path()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Planner::new(config, answers)?.plan(root, |source| {
+            targets.iter().any(|target| source.starts_with(target))
+        })
+    }
+
+    /// Returns the operations that write a file to the project.
+    pub fn writes(&self) -> impl Iterator<Item = &Operation> {
+        self.operations.iter().filter(|operation| {
+            !matches!(operation.kind, OperationKind::Skip | OperationKind::Exists)
+        })
+    }
+
+    /// Leave files that already exist in `destination` untouched.
+    ///
+    /// Returns the paths of the files that will not be written.
+    pub fn keep_existing(&mut self, destination: &Path) -> Vec<PathBuf> {
+        let mut kept = Vec::new();
+        for operation in &mut self.operations {
+            if operation.kind != OperationKind::Skip && destination.join(&operation.target).exists()
+            {
+                operation.kind = OperationKind::Exists;
+                operation.contents = None;
+                kept.push(operation.target.clone());
+            }
+        }
+        kept
+    }
+
+    /// Apply the plan, copying files from the `template` into `destination`.
+    pub fn apply(&self, template: &Path, destination: &Path) -> Result<()> {
+        for operation in self.writes() {
+            let target = destination.join(&operation.target);
+            tracing::debug!("writing {}", operation.target.display());
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            match &operation.contents {
+                Some(contents) => std::fs::write(&target, contents)?,
+                None => {
+                    std::fs::copy(template.join(&operation.source), &target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A rename action of an active question.
+struct Rename {
+    globs: GlobSet,
+    name: String,
+    folder: bool,
+}
+
+struct Planner<'a> {
+    config: &'a Config,
+    answers: &'a Answers,
+    content: ContentMatcher,
+    /// Files that are only part of the project when explicitly included.
+    optional: GlobSet,
+    /// Target paths of all components, the only template paths rendered.
+    optional_targets: Vec<&'a Path>,
+    /// Files explicitly included by the answers.
+    included: GlobSet,
+    included_targets: Vec<&'a Path>,
+    renames: Vec<Rename>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(config: &'a Config, answers: &'a Answers) -> Result<Self> {
+        let active = config.active_questions(answers);
+        let selected = config.selected_components(answers);
+
+        let component_targets = |include: &dyn Fn(&str) -> bool| -> Vec<&'a Path> {
+            config
+                .components
+                .iter()
+                .filter(|component| include(&component.id))
+                .flat_map(|component| &component.targets)
+                .map(|target| Path::new(target.path()))
+                .collect()
+        };
+
+        let mut optional = GlobSetBuilder::new();
+        let mut included = GlobSetBuilder::new();
+        let mut renames = Vec::new();
+        for question in config.all_questions() {
+            let is_active = active.iter().any(|active| std::ptr::eq(*active, question));
+            for action in &question.actions {
+                match action {
+                    QuestionAction::IncludeFolder { glob: Some(glob) } => {
+                        optional.add(compile_glob(glob)?);
+                        if is_active {
+                            included.add(compile_glob(glob)?);
+                        }
+                    }
+                    QuestionAction::RenameFolder { glob, name } if is_active => {
+                        renames.push(Rename::new(glob, name, true)?);
+                    }
+                    QuestionAction::RenameFile { glob, name } if is_active => {
+                        renames.push(Rename::new(glob, name, false)?);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Planner {
+            config,
+            answers,
+            content: ContentMatcher::new(config, answers)?,
+            optional: build_globs(optional)?,
+            optional_targets: component_targets(&|_| true),
+            included: build_globs(included)?,
+            included_targets: component_targets(&|id| selected.contains(&id)),
+            renames,
+        })
+    }
+
+    fn plan(&self, root: &Path, filter: impl Fn(&Path) -> bool) -> Result<Plan> {
+        let mut sources = Vec::new();
+        walk(root, Path::new(""), &mut sources)?;
+
+        let mut plan = Plan::default();
+        let mut targets: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for source in sources.into_iter().filter(|source| filter(source)) {
+            let operation = self.operation(root, source)?;
+
+            if operation.kind != OperationKind::Skip
+                && let Some(first) =
+                    targets.insert(operation.target.clone(), operation.source.clone())
+            {
+                return Err(Error::PlanConflict {
+                    target: operation.target,
+                    first,
+                    second: operation.source,
+                });
+            }
+            plan.operations.push(operation);
+        }
+
+        for (target, source) in &targets {
+            if let Some(parent) = target
+                .ancestors()
+                .skip(1)
+                .find(|dir| targets.contains_key(*dir))
+            {
+                return Err(Error::PlanConflict {
+                    target: parent.to_path_buf(),
+                    first: targets[parent].clone(),
+                    second: source.clone(),
+                });
+            }
+        }
+
+        Ok(plan)
+    }
+
+    fn operation(&self, root: &Path, source: PathBuf) -> Result<Operation> {
+        let is_optional = self.optional.is_match(&source)
+            || self
+                .optional_targets
+                .iter()
+                .any(|target| source.starts_with(target));
+        let is_included = self.included.is_match(&source)
+            || self
+                .included_targets
+                .iter()
+                .any(|target| source.starts_with(target));
+
+        if is_optional && !is_included {
+            return Ok(Operation {
+                kind: OperationKind::Skip,
+                target: source.clone(),
+                source,
+                contents: None,
+            });
+        }
+
+        let target = self.target(&source)?;
+
+        let contents = if self.content.matches(&source) {
+            match std::fs::read_to_string(root.join(&source)) {
+                Ok(contents) => {
+                    let name = source.display().to_string();
+                    Some(render_content(&name, &contents, self.config, self.answers)?)
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => None,
+                Err(err) => return Err(err.into()),
+            }
+        } else {
+            None
+        };
+
+        let kind = match (&contents, target == source) {
+            (Some(_), _) => OperationKind::Rewrite,
+            (None, true) => OperationKind::Copy,
+            (None, false) => OperationKind::Rename,
+        };
+
+        Ok(Operation {
+            kind,
+            source,
+            target,
+            contents,
+        })
+    }
+
+    /// Render the path of a file in the project.
+    ///
+    /// Each segment is replaced by the rendered name of the last rename action
+    /// matching the template path up to that segment. Otherwise only segments
+    /// of component target paths have their placeholders rendered, so other
+    /// bracketed names such as `[slug]` are copied as is.
+    fn target(&self, source: &Path) -> Result<PathBuf> {
+        let segments: Vec<_> = source.iter().collect();
+
+        let mut target = PathBuf::new();
+        let mut prefix = PathBuf::new();
+        for (index, segment) in segments.iter().enumerate() {
+            prefix.push(segment);
+            let is_file = index == segments.len() - 1;
+
+            let rename = self
+                .renames
+                .iter()
+                .rev()
+                .find(|rename| rename.folder != is_file && rename.globs.is_match(&prefix));
+            if let Some(rename) = rename {
+                target.push(render_path(&rename.name, self.answers)?);
+            } else if self
+                .optional_targets
+                .iter()
+                .any(|path| path.starts_with(&prefix))
+            {
+                target.push(render_path(&segment.to_string_lossy(), self.answers)?);
+            } else {
+                target.push(segment);
+            }
+        }
+
+        Ok(target)
+    }
+}
+
+impl Rename {
+    fn new(glob: &str, name: &str, folder: bool) -> Result<Self> {
+        let mut globs = GlobSetBuilder::new();
+        globs.add(compile_glob(glob)?);
+        Ok(Rename {
+            globs: build_globs(globs)?,
+            name: name.to_string(),
+            folder,
+        })
+    }
+}
+
+fn build_globs(builder: GlobSetBuilder) -> Result<GlobSet> {
+    builder.build().map_err(|source| Error::InvalidGlob {
+        glob: source.glob().unwrap_or_default().to_string(),
+        source,
+    })
+}
+
+/// Collect the paths of all template files relative to the template root.
+///
+/// The git directory and the manifest are not part of the template contents.
+fn walk(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries =
+        std::fs::read_dir(root.join(relative))?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = relative.join(entry.file_name());
+        if path == Path::new(".git") || path == Path::new(MANIFEST_FILE) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            walk(root, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::answers::Answer;
+
+    const MANIFEST: &str = r#"
+components:
+  - id: 'web'
+    description: 'Web app'
+    kind: 'folder'
+    targets:
+      - kind: 'folder'
+        path: 'packages/[frontend]'
+questions:
+  - id: 'with_web'
+    prompt: 'Web app?'
+    kind: 'boolean'
+    component: 'web'
+    actions:
+      - kind: 'include_folder'
+    subquestions:
+      - id: 'frontend'
+        prompt: 'Web app name?'
+        kind: 'string'
+  - id: 'project_name'
+    prompt: 'Name?'
+    kind: 'string'
+    actions:
+      - kind: 'rename_file'
+        glob: 'README.md'
+        name: '[project_name].md'
+"#;
+
+    fn template(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+        dir
+    }
+
+    fn answers() -> Answers {
+        let mut answers = Answers::new();
+        answers.insert("with_web", Answer::Bool(true));
+        answers.insert("frontend", Answer::String("site".to_string()));
+        answers.insert("project_name", Answer::String("demo".to_string()));
+        answers
+    }
+
+    fn targets(plan: &Plan) -> Vec<&Path> {
+        plan.writes()
+            .map(|operation| operation.target.as_path())
+            .collect()
+    }
+
+    #[test]
+    fn renders_target_paths_and_renames() {
+        let dir = template(&["README.md", "packages/[frontend]/index.ts"]);
+        let config = Config::parse(MANIFEST, "manifest").unwrap();
+
+        let plan = Plan::new(dir.path(), &config, &answers()).unwrap();
+        assert_eq!(
+            targets(&plan),
+            [Path::new("demo.md"), Path::new("packages/site/index.ts")]
+        );
+    }
+
+    #[test]
+    fn copies_other_bracketed_paths_as_is() {
+        let dir = template(&[
+            "packages/[frontend]/app/[slug]/page.tsx",
+            "pages/[...all].ts",
+            "src/[project_name].rs",
+        ]);
+        let config = Config::parse(MANIFEST, "manifest").unwrap();
+
+        let plan = Plan::new(dir.path(), &config, &answers()).unwrap();
+        assert_eq!(
+            targets(&plan),
+            [
+                Path::new("packages/site/app/[slug]/page.tsx"),
+                Path::new("pages/[...all].ts"),
+                Path::new("src/[project_name].rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unselected_components() {
+        let dir = template(&["README.md", "packages/[frontend]/index.ts"]);
+        let config = Config::parse(MANIFEST, "manifest").unwrap();
+        let mut answers = answers();
+        answers.insert("with_web", Answer::Bool(false));
+
+        let plan = Plan::new(dir.path(), &config, &answers).unwrap();
+        assert_eq!(targets(&plan), [Path::new("demo.md")]);
+    }
+
+    #[test]
+    fn rejects_duplicate_rename_targets() {
+        let dir = template(&["README.md", "demo.md"]);
+        let config = Config::parse(MANIFEST, "manifest").unwrap();
+
+        let err = Plan::new(dir.path(), &config, &answers()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PlanConflict { target, .. } if target == Path::new("demo.md")
+        ));
+    }
+
+    #[test]
+    fn rejects_files_replacing_folders() {
+        let dir = template(&["README.md", "demo.md/notes.txt"]);
+        let config = Config::parse(MANIFEST, "manifest").unwrap();
+
+        let err = Plan::new(dir.path(), &config, &answers()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PlanConflict { target, .. } if target == Path::new("demo.md")
+        ));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use miette::SourceSpan;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+/// A node in the YAML document, located by its source span.
+#[derive(Debug, Clone)]
+pub(crate) struct SpannedNode {
+    /// Span of the node itself.
+    pub value: SourceSpan,
+    /// The raw value of the node if it is a scalar.
+    pub scalar: Option<String>,
+}
+
+/// Index of the source spans in a YAML document keyed by their path.
+///
+/// Paths use the same notation as `serde_yaml`, such as `questions[1].kind`.
+#[derive(Debug, Default)]
+pub(crate) struct SpanIndex {
+    nodes: HashMap<String, SpannedNode>,
+}
+
+impl SpanIndex {
+    /// Build the index for the given source.
+    ///
+    /// Returns an empty index if the source is not valid YAML, as syntax errors
+    /// are reported by the deserializer instead.
+    pub fn new(source: &str) -> Self {
+        let mut builder = Builder {
+            offsets: source.char_indices().map(|(offset, _)| offset).collect(),
+            len: source.len(),
+            index: SpanIndex::default(),
+            stack: Vec::new(),
+        };
+
+        let mut parser = Parser::new_from_str(source);
+        if parser.load(&mut builder, false).is_err() {
+            return SpanIndex::default();
+        }
+
+        builder.index
+    }
+
+    /// Returns the node at the given path.
+    pub fn get(&self, path: &str) -> Option<&SpannedNode> {
+        self.nodes.get(path)
+    }
+
+    /// Returns the span of the node at the given path.
+    pub fn span(&self, path: &str) -> Option<SourceSpan> {
+        self.get(path).map(|node| node.value)
+    }
+
+    /// Iterates over all nodes with their paths.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SpannedNode)> {
+        self.nodes.iter().map(|(path, node)| (path.as_str(), node))
+    }
+}
+
+enum Frame {
+    Mapping {
+        path: String,
+        key: Option<String>,
+    },
+    Sequence {
+        path: String,
+        index: usize,
+    },
+}
+
+struct Builder {
+    /// Byte offsets of every character, as the parser reports char indices.
+    offsets: Vec<usize>,
+    len: usize,
+    index: SpanIndex,
+    stack: Vec<Frame>,
+}
+
+impl Builder {
+    fn offset(&self, mark: &Marker) -> usize {
+        self.offsets.get(mark.index()).copied().unwrap_or(self.len)
+    }
+
+    fn span(&self, mark: &Marker, chars: usize) -> SourceSpan {
+        let start = self.offset(mark);
+        let end = self
+            .offsets
+            .get(mark.index() + chars)
+            .copied()
+            .unwrap_or(self.len);
+        (start, end - start).into()
+    }
+
+    /// Returns the path of the next child node.
+    fn next_child(&mut self) -> String {
+        match self.stack.last_mut() {
+            Some(Frame::Mapping { path, key }) => match key.take() {
+                Some(key) if path.is_empty() => key,
+                Some(key) => format!("{path}.{key}"),
+                None => path.clone(),
+            },
+            Some(Frame::Sequence { path, index }) => {
+                let child = format!("{path}[{index}]");
+                *index += 1;
+                child
+            }
+            None => String::new(),
+        }
+    }
+
+    fn insert(&mut self, path: String, value: SourceSpan, scalar: Option<String>) {
+        self.index.nodes.insert(path, SpannedNode { value, scalar });
+    }
+}
+
+impl MarkedEventReceiver for Builder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, style, ..) => {
+                let chars = match style {
+                    TScalarStyle::SingleQuoted | TScalarStyle::DoubleQuoted => {
+                        value.chars().count() + 2
+                    }
+                    _ => value.chars().count().max(1),
+                };
+                let span = self.span(&mark, chars);
+
+                if let Some(Frame::Mapping { key: key @ None, .. }) = self.stack.last_mut() {
+                    *key = Some(value);
+                    return;
+                }
+
+                let path = self.next_child();
+                self.insert(path, span, Some(value));
+            }
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                let path = self.next_child();
+                self.insert(path.clone(), self.span(&mark, 1), None);
+                self.stack.push(match event {
+                    Event::MappingStart(..) => Frame::Mapping { path, key: None },
+                    _ => Frame::Sequence { path, index: 0 },
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            Event::Alias(_) => {
+                let path = self.next_child();
+                self.insert(path, self.span(&mark, 1), None);
+            }
+            _ => {}
+        }
+    }
+}
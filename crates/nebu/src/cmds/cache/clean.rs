@@ -0,0 +1,33 @@
+use nebu_cache::format_size;
+use owo_colors::OwoColorize;
+
+use super::{describe_source, lock_entry};
+use crate::{
+    GlobalArgs,
+    error::{CommandError, CommandResult},
+};
+
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct CleanArgs {
+    /// Id of the entry, or a unique prefix of it.
+    entry: String,
+}
+
+pub(crate) fn run(global_args: &GlobalArgs, args: CleanArgs) -> CommandResult {
+    tracing::trace!("running cache clean command");
+
+    let entry = super::find_entry(global_args, &args.entry)?;
+    let _lock = lock_entry(global_args, &entry)?;
+
+    let size = entry.size().map_err(CommandError::from_cache)?;
+    entry.remove().map_err(CommandError::from_cache)?;
+    eprintln!(
+        "{} {} ({}, {})",
+        "removed".red(),
+        entry.id,
+        describe_source(&entry),
+        format_size(size)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use nebu_cache::format_size;
+use owo_colors::OwoColorize;
+
+use super::format_age;
+use super::list::EntryOutput;
+use crate::{GlobalArgs, OutputFormats, error::CommandResult};
+
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct InfoArgs {
+    /// Id of the entry, or a unique prefix of it.
+    entry: String,
+}
+
+pub(crate) fn run(global_args: &GlobalArgs, args: InfoArgs) -> CommandResult {
+    tracing::trace!("running cache info command");
+
+    let entry = super::find_entry(global_args, &args.entry)?;
+    let output = EntryOutput::new(&entry)?;
+
+    match global_args.format {
+        OutputFormats::Text => {
+            let time = |time: Option<u64>| time.map_or("unknown".to_string(), format_age);
+            let none = || "none".dimmed().to_string();
+
+            println!("{:<10} {}", "id".bold(), output.id);
+            println!("{:<10} {}", "path".bold(), output.path);
+            println!("{:<10} {}", "url".bold(), output.url.unwrap_or_else(none));
+            println!(
+                "{:<10} {}",
+                "branch".bold(),
+                output.branch.unwrap_or_else(none)
+            );
+            println!(
+                "{:<10} {}",
+                "revision".bold(),
+                output.revision.unwrap_or_else(none)
+            );
+            println!("{:<10} {}", "size".bold(), format_size(output.size));
+            println!("{:<10} {}", "created".bold(), time(output.created_at));
+            println!("{:<10} {}", "refreshed".bold(), time(output.refreshed_at));
+            println!("{:<10} {}", "used".bold(), time(output.used_at));
+            if !output.complete {
+                println!(
+                    "{} the entry is incomplete and is populated again on next use",
+                    "warning:".yellow()
+                );
+            }
+        }
+        OutputFormats::Json => {
+            let json = serde_json::to_string_pretty(&output)?;
+            println!("{json}");
+        }
+        #[cfg(feature = "schema")]
+        OutputFormats::JsonSchema => {
+            let schema = schemars::schema_for!(EntryOutput);
+            let json = serde_json::to_string_pretty(&schema)?;
+            println!("{json}")
+        }
+    };
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use nebu_cache::{CacheEntry, format_size};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use super::{describe_source, format_age};
+use crate::{
+    GlobalArgs, OutputFormats,
+    error::{CommandError, CommandResult},
+};
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub(crate) struct EntryOutput {
+    /// Id of the entry, which is the name of its directory.
+    pub(super) id: String,
+    /// Directory where the entry is stored.
+    pub(super) path: String,
+    /// Whether the entry was completely populated.
+    pub(super) complete: bool,
+    /// URL or path the entry was fetched from.
+    pub(super) url: Option<String>,
+    /// Branch tracked by the entry.
+    pub(super) branch: Option<String>,
+    /// Tag or commit the entry is pinned to.
+    pub(super) revision: Option<String>,
+    /// When the entry was first populated, in seconds since the Unix epoch.
+    pub(super) created_at: Option<u64>,
+    /// When the entry was last refreshed, in seconds since the Unix epoch.
+    pub(super) refreshed_at: Option<u64>,
+    /// When the entry was last used, in seconds since the Unix epoch.
+    pub(super) used_at: Option<u64>,
+    /// Size of the entry on disk in bytes.
+    pub(super) size: u64,
+    /// Version of the scheme used to derive the entry id.
+    pub(super) hash_version: Option<u32>,
+    /// Short description of the source for text output.
+    #[serde(skip)]
+    pub(super) source: String,
+}
+
+impl EntryOutput {
+    pub(super) fn new(entry: &CacheEntry) -> CommandResult<Self> {
+        let metadata = entry.metadata.as_ref();
+        Ok(EntryOutput {
+            id: entry.id.clone(),
+            path: entry.location.display().to_string(),
+            complete: entry.complete,
+            url: metadata.map(|metadata| metadata.source.url.clone()),
+            branch: metadata.and_then(|metadata| metadata.source.branch.clone()),
+            revision: metadata.and_then(|metadata| metadata.source.revision.clone()),
+            created_at: metadata.map(|metadata| metadata.created_at),
+            refreshed_at: metadata.map(|metadata| metadata.refreshed_at),
+            used_at: metadata.map(|metadata| metadata.used_at),
+            size: entry.size().map_err(CommandError::from_cache)?,
+            hash_version: metadata.map(|metadata| metadata.hash_version),
+            source: describe_source(entry),
+        })
+    }
+}
+
+impl fmt::Display for EntryOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let used = self.used_at.map_or("unknown".to_string(), format_age);
+        write!(
+            f,
+            "{:<20}  {:>10}  {:<14}  {}",
+            self.id.bold(),
+            format_size(self.size).dimmed(),
+            used,
+            self.source
+        )?;
+        if !self.complete {
+            write!(f, " {}", "(incomplete)".yellow())?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn run(global_args: &GlobalArgs) -> CommandResult {
+    tracing::trace!("running cache list command");
+
+    let entries = CacheEntry::list(&global_args.cache_path).map_err(CommandError::from_cache)?;
+    let output = entries
+        .iter()
+        .map(EntryOutput::new)
+        .collect::<CommandResult<Vec<_>>>()?;
+
+    match global_args.format {
+        OutputFormats::Text => {
+            for entry in &output {
+                println!("{entry}");
+            }
+            let total = output.iter().map(|entry| entry.size).sum();
+            println!("{} entries, {}", output.len(), format_size(total));
+        }
+        OutputFormats::Json => {
+            let json = serde_json::to_string_pretty(&output)?;
+            println!("{json}");
+        }
+        #[cfg(feature = "schema")]
+        OutputFormats::JsonSchema => {
+            let schema = schemars::schema_for!(Vec<EntryOutput>);
+            let json = serde_json::to_string_pretty(&schema)?;
+            println!("{json}")
+        }
+    };
+
+    Ok(())
+}
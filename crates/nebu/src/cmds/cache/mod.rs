@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use nebu_cache::{CacheEntry, CacheLock};
+use owo_colors::OwoColorize;
+
+use crate::error::{CommandError, CommandResult};
+
+mod clean;
+mod info;
+mod list;
+mod prune;
+mod refresh;
+
+#[derive(clap::Args)]
+pub(crate) struct Cache {
+    #[command(subcommand)]
+    command: CacheCmds,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub(crate) enum CacheCmds {
+    /// List the entries in the cache
+    List,
+    /// Show the details of a cache entry
+    Info(info::InfoArgs),
+    /// Fetch the latest changes of cache entries
+    Refresh(refresh::RefreshArgs),
+    /// Remove a cache entry
+    Clean(clean::CleanArgs),
+    /// Remove unused and incomplete cache entries
+    Prune(prune::PruneArgs),
+}
+
+/// Lock a cache entry, reporting when waiting for another process.
+pub(crate) fn lock_entry(
+    global_args: &crate::GlobalArgs,
+    entry: &CacheEntry,
+) -> CommandResult<CacheLock> {
+    let timeout = Duration::from_secs(global_args.lock_timeout);
+    entry
+        .lock(timeout, |pid| match pid {
+            Some(pid) => eprintln!("{} for lock held by pid {pid}", "waiting".yellow()),
+            None => eprintln!("{} for lock on {}", "waiting".yellow(), entry.id),
+        })
+        .map_err(CommandError::from_cache)
+}
+
+/// Find the cache entry with the given id, or the only one starting with it.
+pub(crate) fn find_entry(global_args: &crate::GlobalArgs, id: &str) -> CommandResult<CacheEntry> {
+    let entries = CacheEntry::list(&global_args.cache_path).map_err(CommandError::from_cache)?;
+    if let Some(entry) = entries.iter().find(|entry| entry.id == id) {
+        return Ok(entry.clone());
+    }
+
+    let mut matches = entries.into_iter().filter(|entry| entry.id.starts_with(id));
+    match (matches.next(), matches.next()) {
+        (Some(entry), None) => Ok(entry),
+        (Some(_), Some(_)) => Err(anyhow::anyhow!(
+            "`{id}` matches multiple cache entries, use a longer prefix"
+        )
+        .into()),
+        (None, _) => Err(anyhow::anyhow!(
+            "no cache entry `{id}` in {}, use `nebu cache list` to see all entries",
+            global_args.cache_path.display()
+        )
+        .into()),
+    }
+}
+
+/// Describe the source of an entry, such as `https://host/repo.git@main`.
+pub(crate) fn describe_source(entry: &CacheEntry) -> String {
+    let Some(metadata) = &entry.metadata else {
+        return "unknown source".to_string();
+    };
+
+    let source = &metadata.source;
+    match source.revision.as_ref().or(source.branch.as_ref()) {
+        Some(reference) => format!("{}@{reference}", source.url),
+        None => source.url.clone(),
+    }
+}
+
+/// Format a time in seconds since the Unix epoch relative to now.
+pub(crate) fn format_age(time: u64) -> String {
+    let age = nebu_cache::now().saturating_sub(time);
+
+    let (amount, unit) = match age {
+        0..60 => return "just now".to_string(),
+        60..3_600 => (age / 60, "minute"),
+        3_600..86_400 => (age / 3_600, "hour"),
+        _ => (age / 86_400, "day"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{amount} {unit}{plural} ago")
+}
+
+pub(crate) fn run(cache: Cache, global_args: &crate::GlobalArgs) -> CommandResult {
+    match cache.command {
+        CacheCmds::List => list::run(global_args),
+        CacheCmds::Info(info_args) => info::run(global_args, info_args),
+        CacheCmds::Refresh(refresh_args) => refresh::run(global_args, refresh_args),
+        CacheCmds::Clean(clean_args) => clean::run(global_args, clean_args),
+        CacheCmds::Prune(prune_args) => prune::run(global_args, prune_args),
+    }
+}
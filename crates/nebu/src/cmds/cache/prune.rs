@@ -0,0 +1,159 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use nebu_cache::{CacheEntry, Error, format_size, now, parse_duration};
+use owo_colors::OwoColorize;
+
+use super::describe_source;
+use crate::{
+    GlobalArgs,
+    error::{CommandError, CommandResult},
+};
+
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct PruneArgs {
+    /// Remove entries that were not used for this long, such as `30d`.
+    ///
+    /// Supports the units `s`, `m`, `h`, `d` and `w`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    older_than: Option<Duration>,
+    /// Remove the least recently used entries until the cache is at most this
+    /// large, such as `2G`.
+    ///
+    /// Sizes use binary units, so `2G` is 2 GiB.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+    /// Print the entries that would be removed without removing them.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// A cache entry with what is needed to decide whether to prune it.
+struct Candidate {
+    entry: CacheEntry,
+    used_at: u64,
+    size: u64,
+}
+
+/// Remove incomplete entries, and entries that are unused or do not fit.
+///
+/// Incomplete entries and staging directories left behind by interrupted
+/// fetches are always removed. Entries in use by another process are skipped.
+pub(crate) fn run(global_args: &GlobalArgs, args: PruneArgs) -> CommandResult {
+    tracing::trace!("running cache prune command");
+
+    let now = now();
+    let mut keep = Vec::new();
+    let mut remove = Vec::new();
+    for entry in CacheEntry::list(&global_args.cache_path).map_err(CommandError::from_cache)? {
+        let candidate = Candidate {
+            used_at: used_at(&entry)?,
+            size: entry.size().map_err(CommandError::from_cache)?,
+            entry,
+        };
+
+        let unused = args
+            .older_than
+            .is_some_and(|age| now.saturating_sub(candidate.used_at) > age.as_secs());
+        if !candidate.entry.complete {
+            remove.push((candidate, "incomplete"));
+        } else if unused {
+            remove.push((candidate, "unused"));
+        } else {
+            keep.push(candidate);
+        }
+    }
+
+    for entry in
+        CacheEntry::list_staging(&global_args.cache_path).map_err(CommandError::from_cache)?
+    {
+        let candidate = Candidate {
+            used_at: used_at(&entry)?,
+            size: entry.size().map_err(CommandError::from_cache)?,
+            entry,
+        };
+        remove.push((candidate, "interrupted"));
+    }
+
+    if let Some(max_size) = args.max_size {
+        keep.sort_by_key(|candidate| candidate.used_at);
+        let mut total: u64 = keep.iter().map(|candidate| candidate.size).sum();
+        for candidate in keep {
+            if total <= max_size {
+                break;
+            }
+            total -= candidate.size;
+            remove.push((candidate, "over the size limit"));
+        }
+    }
+
+    let mut freed = 0;
+    let mut removed = 0;
+    for (candidate, reason) in remove {
+        let Candidate { entry, size, .. } = candidate;
+        let description = format!(
+            "({}, {}, {reason})",
+            describe_source(&entry),
+            format_size(size)
+        );
+        if args.dry_run {
+            println!("{} {} {description}", "would remove".yellow(), entry.id);
+            continue;
+        }
+
+        // Entries locked by other processes are in use, so they are left alone
+        // instead of waiting for them.
+        let _lock = match entry.lock(Duration::ZERO, |_| {}) {
+            Ok(lock) => lock,
+            Err(Error::LockTimeout { .. }) => {
+                eprintln!("{} {} (in use)", "skipped".yellow(), entry.id);
+                continue;
+            }
+            Err(err) => return Err(CommandError::from_cache(err)),
+        };
+        entry.remove().map_err(CommandError::from_cache)?;
+        eprintln!("{} {} {description}", "removed".red(), entry.id);
+        freed += size;
+        removed += 1;
+    }
+
+    if !args.dry_run {
+        println!("removed {removed} entries, freed {}", format_size(freed));
+    }
+    Ok(())
+}
+
+/// When an entry was last used, falling back to when its directory changed
+/// for entries without metadata.
+fn used_at(entry: &CacheEntry) -> CommandResult<u64> {
+    if let Some(metadata) = &entry.metadata {
+        return Ok(metadata.used_at);
+    }
+    let modified = std::fs::metadata(&entry.location)?.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs()))
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    let (amount, unit) = value.split_at(
+        value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len()),
+    );
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("expected a size such as `2G`, got `{value}`"))?;
+    let exponent = match unit.trim_end_matches("iB").trim_end_matches('B') {
+        "" => 0,
+        "K" | "k" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => {
+            return Err(format!(
+                "unknown unit `{unit}`, expected one of B, K, M, G or T"
+            ));
+        }
+    };
+    Ok(amount.saturating_mul(1024u64.pow(exponent)))
+}
@@ -0,0 +1,135 @@
+use std::hash::Hash;
+use std::path::Path;
+
+use git2::Oid;
+use nebu_cache::{
+    ArchiveCache, ArchiveFormat, CacheEntry, CacheManager, Error, LocalCache, Refresh, RepoCache,
+    Revision, Source, SourceKind,
+};
+use owo_colors::OwoColorize;
+
+use super::{describe_source, lock_entry};
+use crate::{
+    GlobalArgs,
+    cmds::project::credentials,
+    error::{CommandError, CommandResult},
+};
+
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct RefreshArgs {
+    /// Id of the entry, or a unique prefix of it.
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    entry: Option<String>,
+    /// Refresh all entries in the cache.
+    ///
+    /// Entries that fail to refresh are reported, and the remaining entries
+    /// are still refreshed.
+    #[arg(long)]
+    all: bool,
+}
+
+pub(crate) fn run(global_args: &GlobalArgs, args: RefreshArgs) -> CommandResult {
+    tracing::trace!("running cache refresh command");
+
+    let entries = match &args.entry {
+        Some(id) => vec![super::find_entry(global_args, id)?],
+        None => CacheEntry::list(&global_args.cache_path).map_err(CommandError::from_cache)?,
+    };
+    let credentials = credentials::load(global_args)?;
+
+    let mut failed = 0;
+    for entry in entries {
+        let Some(source) = entry.metadata.as_ref().map(|metadata| &metadata.source) else {
+            skip(&entry);
+            continue;
+        };
+
+        // Local templates can be refreshed offline, repositories can't.
+        if global_args.offline && source.kind == SourceKind::Git {
+            if !args.all {
+                return Err(CommandError::offline("refresh a template repository"));
+            }
+            eprintln!(
+                "{} {} ({}, offline)",
+                "skipped".yellow(),
+                entry.id,
+                describe_source(&entry)
+            );
+            continue;
+        }
+
+        let _lock = lock_entry(global_args, &entry)?;
+        let result = match source.kind {
+            SourceKind::Git => repo_cache(source).map(|repo| {
+                let repo = repo
+                    .with_credentials(credentials.clone())
+                    .with_progress(global_args.show_progress());
+                refresh_entry(&entry, repo)
+            }),
+            SourceKind::Archive => ArchiveFormat::from_path(Path::new(&source.url))
+                .map(|format| refresh_entry(&entry, ArchiveCache::new(&source.url, format))),
+            SourceKind::Directory => Some(refresh_entry(&entry, LocalCache::new(&source.url))),
+        };
+        let Some(result) = result else {
+            skip(&entry);
+            continue;
+        };
+
+        match result {
+            Ok(_) => eprintln!(
+                "{} {} ({})",
+                "refreshed".green(),
+                entry.id,
+                describe_source(&entry)
+            ),
+            Err(err) if args.all => {
+                eprintln!(
+                    "{} {} ({}): {err}",
+                    "failed".red(),
+                    entry.id,
+                    describe_source(&entry)
+                );
+                failed += 1;
+            }
+            Err(err) => return Err(CommandError::from_cache(err)),
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("failed to refresh {failed} cache entries").into());
+    }
+    Ok(())
+}
+
+/// Report an entry that can't be refreshed because its source is unknown.
+fn skip(entry: &CacheEntry) {
+    eprintln!(
+        "{} {} (unknown source, remove it with `nebu cache clean`)",
+        "skipped".yellow(),
+        entry.id
+    );
+}
+
+/// Refresh the data of an entry with the given cache.
+fn refresh_entry<T: Refresh + Hash>(entry: &CacheEntry, inner: T) -> Result<bool, Error> {
+    CacheManager::open(entry.location.clone(), inner).refresh()
+}
+
+/// Create the repository cache for an entry from its recorded source.
+///
+/// Returns `None` if the source does not describe a repository branch.
+fn repo_cache(source: &Source) -> Option<RepoCache> {
+    // Entries created before the remote was recorded always used `origin`.
+    let remote = source.remote.as_deref().unwrap_or("origin");
+    let mut repo = RepoCache::new(&source.url, source.branch.as_deref()?, remote);
+    if let Some(depth) = source.depth {
+        repo = repo.with_depth(depth);
+    }
+    Some(match &source.revision {
+        Some(revision) => match Oid::from_str(revision) {
+            Ok(oid) if revision.len() == 40 => repo.with_revision(Revision::Commit(oid)),
+            _ => repo.with_revision(Revision::Tag(revision.clone())),
+        },
+        None => repo,
+    })
+}
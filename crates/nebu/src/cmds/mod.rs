@@ -1,3 +1,4 @@
+pub(crate) mod cache;
 pub(crate) mod env;
 pub(crate) mod project;
 pub(crate) mod version;
@@ -1,38 +1,135 @@
-use crate::error::CommandResult;
+use std::path::Path;
+
+use git2::Oid;
+use nebu_cache::Revision;
+use nebu_template::{
+    Answer, Answers, Config, LOCK_FILE, Lockfile, MANIFEST_FILE, Plan, QuestionEngine,
+    TemplateLock, render_path,
+};
+use tempfile::tempdir;
+
+use super::template::{TemplateSource, fetch_template};
+use crate::error::{CommandError, CommandResult};
 
 #[derive(clap::Args, Debug, Clone)]
 pub(crate) struct AddArgs {
-    /// URL of the repository to use as a template.
-    #[arg(
-        short = 'u',
-        long,
-        default_value = "https://github.com/kanerix/nebu-cli.git",
-        env = "NEBU_TEMPLATE_REPO"
-    )]
-    repo_url: String,
-    /// Branch of the repository to use.
-    #[arg(
-        short = 'b',
-        long,
-        default_value = "main",
-        env = "NEBU_TEMPLATE_BRANCH"
-    )]
-    repo_branch: String,
-    /// Remote of the repository to use.
-    #[arg(
-        short = 'r',
-        long,
-        default_value = "origin",
-        env = "NEBU_TEMPLATE_remote"
-    )]
-    repo_remote: String,
+    /// Id or alias of the component to add.
+    component: String,
+    #[command(flatten)]
+    answer_args: super::AnswerArgs,
 }
 
 pub async fn run(
-    _global_args: Box<crate::GlobalArgs>,
-    _project_args: super::ProjectArgs,
-    _init_args: AddArgs,
+    global_args: Box<crate::GlobalArgs>,
+    project_args: super::ProjectArgs,
+    args: AddArgs,
 ) -> CommandResult {
     tracing::trace!("running project add command");
+
+    let project_dir = super::find_project_root()?;
+    let mut lockfile =
+        Lockfile::load_from_dir(&project_dir).map_err(CommandError::from_template)?;
+    tracing::debug!("using project at {}", project_dir.display());
+
+    let tempdir = tempdir()?;
+    let config = match TemplateSource::from_lock(&lockfile.template, &project_dir)? {
+        Some(source) => {
+            source.checkout(tempdir.path(), &global_args, &project_args)?;
+            Config::load_from_dir(tempdir.path()).map_err(CommandError::from_template)?
+        }
+        None => checkout_component(
+            tempdir.path(),
+            &global_args,
+            &project_args,
+            &lockfile.template,
+            &args.component,
+        )?,
+    };
+    let component = config
+        .resolve_component(&args.component)
+        .map_err(CommandError::from_template)?;
+
+    if lockfile.components.contains(&component.id) {
+        return Err(anyhow::anyhow!(
+            "component `{}` is already part of the project",
+            component.id
+        )
+        .into());
+    }
+
+    let mut prompter = args.answer_args.prompter(&config)?;
+    let mut answers = Answers::new();
+    for question in config.component_questions(&component.id) {
+        answers.insert(question.key(), Answer::Bool(true));
+        let subanswers = QuestionEngine::new(&mut prompter)
+            .ask(&question.subquestions)
+            .map_err(CommandError::from_template)?;
+        answers.extend(subanswers);
+    }
+
+    let mut all_answers = lockfile.answers.clone();
+    all_answers.extend(answers.clone());
+
+    for target in &component.targets {
+        let destination =
+            render_path(target.path(), &all_answers).map_err(CommandError::from_template)?;
+        if project_dir.join(&destination).exists() {
+            return Err(anyhow::anyhow!(
+                "cannot add component `{}`, {} already exists",
+                component.id,
+                destination.display()
+            )
+            .into());
+        }
+    }
+
+    let plan = Plan::for_component(tempdir.path(), &config, &component.id, &all_answers)
+        .map_err(CommandError::from_template)?;
+    plan.apply(tempdir.path(), &project_dir)
+        .map_err(CommandError::from_template)?;
+
+    lockfile.components.push(component.id.clone());
+    lockfile.answers.extend(answers);
+    lockfile
+        .write_to_dir(&project_dir)
+        .map_err(CommandError::from_template)?;
+    tracing::info!("added component `{}`, updated {LOCK_FILE}", component.id);
+
     Ok(())
 }
+
+/// Check out the manifest and the folders of `component` from the template
+/// repository into `path`.
+///
+/// Returns the manifest of the template.
+fn checkout_component(
+    path: &Path,
+    global_args: &crate::GlobalArgs,
+    project_args: &super::ProjectArgs,
+    template: &TemplateLock,
+    component: &str,
+) -> CommandResult<Config> {
+    let commit = Oid::from_str(&template.commit).map_err(CommandError::from_git2)?;
+    let repo = project_args
+        .template_repo(
+            global_args,
+            &template.url,
+            &template.branch,
+            &template.remote,
+        )?
+        .with_revision(Revision::Commit(commit));
+
+    // Only the manifest and the folders of the component are needed, so the
+    // manifest is checked out first to find out where the component lives.
+    let fetched = fetch_template(global_args, project_args, &repo)?;
+    fetched.checkout(&repo.clone().with_sparse_paths([MANIFEST_FILE]), path)?;
+
+    let config = Config::load_from_dir(path).map_err(CommandError::from_template)?;
+    let component = config
+        .resolve_component(component)
+        .map_err(CommandError::from_template)?;
+
+    let paths = component.targets.iter().map(|target| target.path());
+    fetched.checkout(&repo.with_sparse_paths(paths), path)?;
+    Ok(config)
+}
@@ -0,0 +1,82 @@
+use nebu_cache::{Credentials, SshKey};
+use nebu_config::CredentialsConfig;
+
+use crate::error::CommandResult;
+
+/// Environment variable with a token for HTTPS remotes.
+const TOKEN_ENV: &str = "NEBU_GIT_TOKEN";
+
+/// Environment variable with the passphrase of the default SSH keys.
+const PASSPHRASE_ENV: &str = "NEBU_SSH_PASSPHRASE";
+
+/// Key files tried in `~/.ssh` when no SSH keys are configured.
+const DEFAULT_SSH_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Build the credentials for fetching templates from the nebu configuration.
+///
+/// The token is read from `NEBU_GIT_TOKEN` if set, otherwise from the
+/// configured environment variable or the configuration itself. Without
+/// configured SSH keys, the default keys in `~/.ssh` are tried after the SSH
+/// agent.
+pub(crate) fn load(global_args: &crate::GlobalArgs) -> CommandResult<Credentials> {
+    let config = global_args.load_config()?;
+    let CredentialsConfig {
+        ssh_keys,
+        token,
+        token_env,
+        username,
+        credential_helper,
+        ssh_agent,
+    } = config.credentials;
+
+    let fallback_ssh_keys = if ssh_keys.is_empty() {
+        default_ssh_keys()
+    } else {
+        Vec::new()
+    };
+    let ssh_keys = ssh_keys
+        .into_iter()
+        .map(|key| {
+            let path = nebu_fs::expand_home_dir(&key.path).unwrap_or(key.path);
+            let passphrase = key.passphrase_env.and_then(|name| env(&name));
+            SshKey { path, passphrase }
+        })
+        .collect();
+
+    let token = env(TOKEN_ENV)
+        .or_else(|| token_env.and_then(|name| env(&name)))
+        .or(token);
+
+    let credentials = Credentials {
+        ssh_keys,
+        fallback_ssh_keys,
+        token,
+        username,
+        credential_helper,
+        ssh_agent,
+    };
+    tracing::debug!("using credentials {credentials:?}");
+    Ok(credentials)
+}
+
+/// The default key files in `~/.ssh` that exist.
+fn default_ssh_keys() -> Vec<SshKey> {
+    let Some(dir) = home::home_dir().map(|home| home.join(".ssh")) else {
+        return Vec::new();
+    };
+
+    DEFAULT_SSH_KEYS
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .map(|path| SshKey {
+            path,
+            passphrase: env(PASSPHRASE_ENV),
+        })
+        .collect()
+}
+
+/// Read a non-empty environment variable.
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
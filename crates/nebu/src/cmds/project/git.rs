@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+use git2::{Config, ErrorCode, IndexAddOption, Repository, RepositoryInitOptions, Signature};
+use nebu_template::TemplateLock;
+
+use crate::error::{CommandError, CommandResult};
+
+/// Initialize a git repository in `path` and commit everything in it.
+///
+/// The commit message references the template the project was generated
+/// from. If a `remote` is given, it is added as `origin`.
+pub(crate) fn init_repository(
+    path: &Path,
+    branch: &str,
+    remote: Option<&str>,
+    template: &TemplateLock,
+    signature: &Signature<'_>,
+) -> CommandResult {
+    init(path, branch, remote, template, signature).map_err(CommandError::from_git2)
+}
+
+/// Find the work directory of the git repository containing `path`, if any.
+///
+/// The path does not need to exist yet, in which case the closest existing
+/// ancestor is checked.
+pub(crate) fn containing_repository(path: &Path) -> CommandResult<Option<PathBuf>> {
+    let path = std::path::absolute(path)?;
+    let Some(existing) = path.ancestors().find(|dir| dir.exists()) else {
+        return Ok(None);
+    };
+
+    match Repository::discover(existing) {
+        // The work directory has a trailing separator, which is dropped.
+        Ok(repo) => Ok(repo.workdir().map(|dir| dir.components().collect())),
+        Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+        Err(err) => Err(CommandError::from_git2(err)),
+    }
+}
+
+/// Create the signature for the initial commit from the user's git config.
+pub(crate) fn signature() -> CommandResult<Signature<'static>> {
+    let config = Config::open_default().map_err(CommandError::from_git2)?;
+    let get = |key: &str| {
+        config.get_string(key).map_err(|_| {
+            anyhow::anyhow!(
+                "cannot create the initial commit, `{key}` is not set in the git config"
+            )
+        })
+    };
+
+    let signature =
+        Signature::now(&get("user.name")?, &get("user.email")?).map_err(CommandError::from_git2)?;
+    Ok(signature)
+}
+
+fn init(
+    path: &Path,
+    branch: &str,
+    remote: Option<&str>,
+    template: &TemplateLock,
+    signature: &Signature<'_>,
+) -> Result<(), git2::Error> {
+    let mut options = RepositoryInitOptions::new();
+    options.initial_head(branch);
+    let repo = Repository::init_opts(path, &options)?;
+    tracing::debug!("initialized git repository in {}", path.display());
+
+    let mut index = repo.index()?;
+    index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+
+    let message = if template.commit.is_empty() {
+        format!("Initial commit\n\nGenerated from {}.", template.url)
+    } else {
+        format!(
+            "Initial commit\n\nGenerated from {} at commit {}.",
+            template.url, template.commit
+        )
+    };
+    let oid = repo.commit(Some("HEAD"), signature, signature, &message, &tree, &[])?;
+    tracing::info!("created initial commit {oid} on branch `{branch}`");
+
+    if let Some(url) = remote {
+        repo.remote("origin", url)?;
+        tracing::info!("added remote `origin` with url {url}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+use std::process::Command;
+
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use nebu_template::{Answers, Hook};
+use owo_colors::OwoColorize;
+
+use crate::error::{CommandError, CommandErrorKind, CommandResult};
+
+/// Run the post-generation hooks of a template in the project directory.
+///
+/// Hooks are only run after confirmation, unless the template is trusted. If
+/// prompting is disabled, untrusted hooks are skipped.
+pub(crate) fn run(
+    hooks: &[&Hook],
+    project_dir: &Path,
+    answers: &Answers,
+    trust: bool,
+    no_input: bool,
+) -> CommandResult {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    if !trust {
+        eprintln!("The template wants to run the following commands:");
+        for hook in hooks {
+            eprintln!("  {}", hook.run.bold());
+        }
+
+        if no_input {
+            eprintln!(
+                "{} hooks, use `--trust-template` to run them without confirmation",
+                "skipped".yellow()
+            );
+            return Ok(());
+        }
+
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Do you want to run these commands?")
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            return Ok(());
+        }
+    }
+
+    for hook in hooks {
+        let dir = match hook.dir(answers).map_err(CommandError::from_template)? {
+            Some(dir) => project_dir.join(dir),
+            None => project_dir.to_path_buf(),
+        };
+        eprintln!("{} {}", "running".cyan(), hook.name());
+        tracing::debug!("running `{}` in {}", hook.run, dir.display());
+
+        let status = shell(&hook.run).current_dir(&dir).status()?;
+        if !status.success() {
+            return Err(CommandError {
+                inner: CommandErrorKind::HookFailed {
+                    name: hook.name().to_string(),
+                    command: hook.run.clone(),
+                    dir,
+                    status,
+                },
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a command running `script` in the platform shell.
+fn shell(script: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(script);
+    command
+}
@@ -1,11 +1,20 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use git2::Oid;
+use nebu_cache::Revision;
+use nebu_template::{LOCK_FILE, Lockfile, Plan, QuestionEngine, TemplateKind, TemplateLock};
+use owo_colors::OwoColorize;
 use tempfile::tempdir;
 
+use super::template::{TemplateSource, checkout_template};
 use crate::error::{CommandError, CommandResult};
 
 #[derive(clap::Args, Debug, Clone)]
 pub(crate) struct InitArgs {
+    /// Directory to generate the project in.
+    ///
+    /// Defaults to the current directory, and is created if it does not exist.
+    path: Option<PathBuf>,
     /// URL of the repository to use as a template.
     #[arg(
         short = 'u',
@@ -14,6 +23,20 @@ pub(crate) struct InitArgs {
         env = "NEBU_TEMPLATE_REPO"
     )]
     repo_url: String,
+    /// Path or `file://` URL of a local template directory or archive to use
+    /// instead of the repository.
+    ///
+    /// Directories are read including uncommitted changes. Archives can be
+    /// `.tar.gz`, `.tgz` or `.zip` files.
+    #[arg(
+        short = 't',
+        long,
+        value_name = "SOURCE",
+        value_parser = TemplateSource::parse,
+        conflicts_with_all = ["repo_tag", "repo_commit"],
+        env = "NEBU_TEMPLATE"
+    )]
+    template: Option<TemplateSource>,
     /// Branch of the repository to use.
     #[arg(
         short = 'b',
@@ -30,6 +53,44 @@ pub(crate) struct InitArgs {
         env = "NEBU_TEMPLATE_remote"
     )]
     repo_remote: String,
+    /// Tag of the repository to use instead of the head of the branch.
+    #[arg(long, conflicts_with = "repo_commit", env = "NEBU_TEMPLATE_TAG")]
+    repo_tag: Option<String>,
+    /// Full OID of the commit of the repository to use instead of the head of
+    /// the branch.
+    #[arg(long, value_name = "OID", value_parser = parse_oid)]
+    repo_commit: Option<Oid>,
+    /// Print the files that would be generated without writing anything.
+    #[arg(long)]
+    dry_run: bool,
+    /// Generate into a non-empty directory, overwriting existing files.
+    #[arg(long, conflicts_with = "merge")]
+    force: bool,
+    /// Generate into a non-empty directory, keeping existing files.
+    ///
+    /// Only files that do not exist yet are written, and every skipped file
+    /// is reported.
+    #[arg(long)]
+    merge: bool,
+    /// Initialize a git repository with an initial commit in the project.
+    ///
+    /// The commit is created after the hooks of the template ran, so it
+    /// includes the files they generate.
+    #[arg(long)]
+    git: bool,
+    /// Name of the default branch of the git repository.
+    ///
+    /// Ignored without `--git`, so it can be set in the environment.
+    #[arg(long, default_value = "main", env = "NEBU_GIT_BRANCH")]
+    git_branch: String,
+    /// URL of the remote to add as `origin` to the git repository.
+    #[arg(long, value_name = "URL", requires = "git")]
+    remote: Option<String>,
+    /// Run the hooks of the template without asking for confirmation.
+    #[arg(long, env = "NEBU_TRUST_TEMPLATE")]
+    trust_template: bool,
+    #[command(flatten)]
+    answer_args: super::AnswerArgs,
 }
 
 pub async fn run(
@@ -39,25 +100,163 @@ pub async fn run(
 ) -> CommandResult {
     tracing::trace!("running project init command");
 
+    let project_dir = match &args.path {
+        Some(path) => path.clone(),
+        None => std::env::current_dir()?,
+    };
+    if !args.force && !args.merge && !is_empty_dir(&project_dir)? {
+        // A preview is most useful for a non-empty directory, so a dry run
+        // only warns about it.
+        if !args.dry_run {
+            return Err(anyhow::anyhow!(
+                "{} is not empty, use `--merge` to keep existing files or `--force` to overwrite them",
+                project_dir.display()
+            )
+            .into());
+        }
+        eprintln!(
+            "{} {} is not empty, generating requires `--merge` or `--force`",
+            "warning:".yellow(),
+            project_dir.display()
+        );
+    }
+    if args.git
+        && let Some(workdir) = super::git::containing_repository(&project_dir)?
+    {
+        return Err(anyhow::anyhow!(
+            "{} is already inside the git repository at {}, run without `--git` to generate into it",
+            project_dir.display(),
+            workdir.display()
+        )
+        .into());
+    }
+    let signature = (args.git && !args.dry_run)
+        .then(super::git::signature)
+        .transpose()?;
+
     let tempdir = tempdir()?;
 
-    clone_to_tempdir(tempdir.path(), &global_args, &project_args, &args)?;
+    let template = match &args.template {
+        Some(source) => {
+            source.checkout(tempdir.path(), &global_args, &project_args)?;
+            source.lock(&project_dir)?
+        }
+        None => checkout_repo(tempdir.path(), &global_args, &project_args, &args)?,
+    };
+
+    let config = nebu_template::Config::load_from_dir(tempdir.path())
+        .map_err(CommandError::from_template)?;
+    tracing::debug!(
+        "loaded template manifest with {} components and {} questions",
+        config.components.len(),
+        config.questions.len()
+    );
+
+    let answers = QuestionEngine::new(args.answer_args.prompter(&config)?)
+        .ask(&config.questions)
+        .map_err(CommandError::from_template)?;
+    let components: Vec<String> = config
+        .selected_components(&answers)
+        .into_iter()
+        .map(String::from)
+        .collect();
+    tracing::debug!("selected components: {components:?}");
+
+    let mut plan =
+        Plan::new(tempdir.path(), &config, &answers).map_err(CommandError::from_template)?;
+    let kept = if args.merge {
+        plan.keep_existing(&project_dir)
+    } else {
+        Vec::new()
+    };
+    if args.dry_run {
+        return super::plan::print(&global_args, tempdir.path(), &plan);
+    }
+
+    for path in &kept {
+        eprintln!("{} {} (already exists)", "skipped".yellow(), path.display());
+    }
+
+    std::fs::create_dir_all(&project_dir)?;
+    plan.apply(tempdir.path(), &project_dir)
+        .map_err(CommandError::from_template)?;
+    tracing::info!(
+        "generated {} files in {}",
+        plan.writes().count(),
+        project_dir.display()
+    );
+
+    Lockfile::new(template.clone(), components, answers.clone())
+        .write_to_dir(&project_dir)
+        .map_err(CommandError::from_template)?;
+    tracing::info!("wrote {LOCK_FILE} to {}", project_dir.display());
+
+    super::hooks::run(
+        &config.active_hooks(&answers),
+        &project_dir,
+        &answers,
+        args.trust_template,
+        args.answer_args.no_input,
+    )?;
+
+    if let Some(signature) = &signature {
+        super::git::init_repository(
+            &project_dir,
+            &args.git_branch,
+            args.remote.as_deref(),
+            &template,
+            signature,
+        )?;
+    }
 
     Ok(())
 }
 
-fn clone_to_tempdir(
+/// Check out the template repository into `path`.
+///
+/// Returns the lock of the revision that was checked out.
+fn checkout_repo(
     path: &Path,
     global_args: &crate::GlobalArgs,
     project_args: &super::ProjectArgs,
     args: &InitArgs,
-) -> CommandResult {
-    if project_args.no_cache {
-        git2::Repository::clone(&args.repo_url, path).map_err(CommandError::from_git2)?;
-    } else {
-        let _ = &global_args.cache_path;
-        todo!("Implement caching logic");
+) -> CommandResult<TemplateLock> {
+    let mut repo = project_args.template_repo(
+        global_args,
+        &args.repo_url,
+        &args.repo_branch,
+        &args.repo_remote,
+    )?;
+    if let Some(tag) = &args.repo_tag {
+        repo = repo.with_revision(Revision::Tag(tag.clone()));
+    } else if let Some(oid) = args.repo_commit {
+        repo = repo.with_revision(Revision::Commit(oid));
     }
+    let commit = checkout_template(path, global_args, project_args, &repo)?;
 
-    Ok(())
+    Ok(TemplateLock {
+        kind: TemplateKind::Git,
+        url: args.repo_url.clone(),
+        branch: args.repo_branch.clone(),
+        remote: args.repo_remote.clone(),
+        tag: args.repo_tag.clone(),
+        commit: commit.to_string(),
+    })
+}
+
+/// Parse a full commit OID, rejecting abbreviated ones.
+fn parse_oid(value: &str) -> Result<Oid, String> {
+    if value.len() != 40 {
+        return Err("expected a full 40 character commit OID".to_string());
+    }
+    Oid::from_str(value).map_err(|err| err.message().to_string())
+}
+
+/// Returns whether the path is an empty directory or does not exist yet.
+fn is_empty_dir(path: &Path) -> CommandResult<bool> {
+    match std::fs::read_dir(path) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(err) => Err(err.into()),
+    }
 }
@@ -1,7 +1,25 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use nebu_cache::{FreshnessPolicy, RepoCache};
+use nebu_template::{
+    Answer, Answers, Config, DialoguerPrompter, LOCK_FILE, PresetPrompter, Prompter,
+};
+use owo_colors::OwoColorize;
+
 use super::project;
+use crate::error::{CommandError, CommandResult};
 
 mod add;
+pub(crate) mod credentials;
+mod git;
+mod hooks;
 mod init;
+mod plan;
+mod template;
+
+/// Check cached templates for changes at most once an hour by default.
+const DEFAULT_REFRESH: FreshnessPolicy = FreshnessPolicy::After(Duration::from_secs(60 * 60));
 
 #[derive(clap::Args)]
 pub(crate) struct Project {
@@ -15,20 +33,146 @@ pub(crate) struct Project {
 
 #[derive(clap::Subcommand, Debug, Clone)]
 pub(crate) enum ProjectCmds {
+    /// Add a component of the template to an existing project.
     Add(add::AddArgs),
-    Init(init::InitArgs),
+    /// Generate a new project from a template.
+    Init(Box<init::InitArgs>),
 }
 
 #[derive(clap::Args, Debug, Clone)]
 pub(crate) struct ProjectArgs {
+    /// Clone the template repository directly instead of using the cache.
     #[arg(
         global = true,
         long,
-        env = "NEBU_TEMPLATE_REPO",
-        default_value = "y",
+        env = "NEBU_NO_CACHE",
+        default_value = "false",
         value_parser = clap::builder::BoolishValueParser::new(),
     )]
     pub no_cache: bool,
+    /// Only fetch this many commits of the template history.
+    ///
+    /// Speeds up fetching templates with a long history. Ignored for templates
+    /// pinned to a commit.
+    #[arg(global = true, long, env = "NEBU_CLONE_DEPTH")]
+    pub depth: Option<u32>,
+    /// When to check the cached template for changes.
+    ///
+    /// Either `always`, `never` or a duration since the last check, such as
+    /// `1h`. Defaults to `cache.refresh` in the configuration, or `1h`.
+    #[arg(global = true, long, env = "NEBU_REFRESH", value_name = "POLICY")]
+    pub refresh: Option<FreshnessPolicy>,
+}
+
+impl ProjectArgs {
+    /// Create the cache for a template repository.
+    ///
+    /// The repository is fetched with the credentials from the nebu
+    /// configuration, showing progress if the output allows it.
+    pub fn template_repo(
+        &self,
+        global_args: &crate::GlobalArgs,
+        url: &str,
+        branch: &str,
+        remote: &str,
+    ) -> CommandResult<RepoCache> {
+        let repo = RepoCache::new(url, branch, remote)
+            .with_credentials(credentials::load(global_args)?)
+            .with_progress(global_args.show_progress());
+        Ok(match self.depth {
+            Some(depth) => repo.with_depth(depth),
+            None => repo,
+        })
+    }
+
+    /// The freshness policy for the template cache.
+    pub fn freshness_policy(
+        &self,
+        global_args: &crate::GlobalArgs,
+    ) -> CommandResult<FreshnessPolicy> {
+        if let Some(policy) = self.refresh {
+            return Ok(policy);
+        }
+
+        match global_args.load_config()?.cache.refresh {
+            Some(policy) => policy.parse().map_err(|err| {
+                anyhow::anyhow!("invalid `cache.refresh` in the configuration: {err}").into()
+            }),
+            None => Ok(DEFAULT_REFRESH),
+        }
+    }
+}
+
+// Arguments for answering template questions without prompting. Not a doc
+// comment, as clap would use it as the about text of the commands flattening
+// these arguments.
+#[derive(clap::Args, Debug, Clone)]
+pub(crate) struct AnswerArgs {
+    /// YAML file with answers to the template questions.
+    ///
+    /// Questions missing from the file use their default answer.
+    #[arg(long, value_name = "FILE")]
+    answers: Option<PathBuf>,
+    /// Answer a template question, overriding the answers file.
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_answer)]
+    set: Vec<(String, String)>,
+    /// Never prompt, failing on questions without an answer or default.
+    #[arg(long, env = "NEBU_NO_INPUT")]
+    no_input: bool,
+}
+
+impl AnswerArgs {
+    /// Create the prompter used to answer the template questions.
+    ///
+    /// Prompts interactively unless answers are given or input is disabled.
+    /// Answers to questions the template doesn't ask are reported, as they are
+    /// most likely misspelled.
+    pub fn prompter(&self, config: &Config) -> CommandResult<Box<dyn Prompter>> {
+        if self.answers.is_none() && self.set.is_empty() && !self.no_input {
+            return Ok(Box::new(DialoguerPrompter::default()));
+        }
+
+        let mut presets = match &self.answers {
+            Some(path) => Answers::load(path).map_err(CommandError::from_template)?,
+            None => Answers::new(),
+        };
+        for (key, value) in &self.set {
+            presets.insert(key, Answer::String(value.clone()));
+        }
+
+        let questions = config.all_questions();
+        for (key, _) in presets.iter() {
+            if !questions.iter().any(|question| question.key() == key) {
+                eprintln!(
+                    "{} the template has no question `{key}`, ignoring its answer",
+                    "warning:".yellow()
+                );
+            }
+        }
+
+        let fallback = (!self.no_input).then(DialoguerPrompter::default);
+        Ok(Box::new(PresetPrompter::new(presets, fallback)))
+    }
+}
+
+/// Find the root of the project containing the current directory.
+///
+/// This is the closest directory with a lockfile, falling back to the current
+/// directory if there is none.
+pub(crate) fn find_project_root() -> CommandResult<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    let root = current_dir
+        .ancestors()
+        .find(|dir| dir.join(LOCK_FILE).is_file())
+        .unwrap_or(&current_dir);
+    Ok(root.to_path_buf())
+}
+
+fn parse_answer(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{value}`"))
 }
 
 pub(crate) async fn run(
@@ -36,11 +180,9 @@ pub(crate) async fn run(
     global_args: Box<crate::GlobalArgs>,
 ) -> crate::error::CommandResult {
     match project.command {
-        ProjectCmds::Add(add_args) => {
-            project::add::run(global_args, project.args, add_args).await
-        }
+        ProjectCmds::Add(add_args) => project::add::run(global_args, project.args, add_args).await,
         ProjectCmds::Init(init_args) => {
-            project::init::run(global_args, project.args, init_args).await
+            project::init::run(global_args, project.args, *init_args).await
         }
     }
 }
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::path::Path;
+
+use nebu_cache::format_size;
+use nebu_template::{OperationKind, Plan};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::{
+    GlobalArgs, OutputFormats,
+    error::{CommandError, CommandResult},
+};
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub(crate) struct PlanOutput {
+    /// Files that would be written to the project, or are skipped because
+    /// they already exist.
+    files: Vec<PlannedFile>,
+    /// Total size of all written files in bytes.
+    total_size: u64,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub(crate) struct PlannedFile {
+    /// What would happen to the file.
+    action: FileAction,
+    /// Path of the file in the template.
+    source: String,
+    /// Path of the file in the project.
+    path: String,
+    /// Size of the file in bytes.
+    size: u64,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FileAction {
+    /// The file is copied from the template.
+    Created,
+    /// The file is copied from the template to a different path.
+    Renamed,
+    /// The file is written with answers rendered into its contents.
+    Rewritten,
+    /// The file already exists in the project and is not written.
+    Skipped,
+}
+
+impl PlanOutput {
+    pub(crate) fn new(template: &Path, plan: &Plan) -> CommandResult<Self> {
+        let mut files = Vec::new();
+        for operation in &plan.operations {
+            let action = match operation.kind {
+                OperationKind::Copy => FileAction::Created,
+                OperationKind::Rename => FileAction::Renamed,
+                OperationKind::Rewrite => FileAction::Rewritten,
+                OperationKind::Exists => FileAction::Skipped,
+                OperationKind::Skip => continue,
+            };
+            files.push(PlannedFile {
+                action,
+                source: operation.source.display().to_string(),
+                path: operation.target.display().to_string(),
+                size: operation
+                    .size(template)
+                    .map_err(CommandError::from_template)?,
+            });
+        }
+
+        let total_size = files
+            .iter()
+            .filter(|file| !matches!(file.action, FileAction::Skipped))
+            .map(|file| file.size)
+            .sum();
+        Ok(PlanOutput { files, total_size })
+    }
+}
+
+impl fmt::Display for FileAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileAction::Created => fmt::Display::fmt(&"created".green(), f),
+            FileAction::Renamed => fmt::Display::fmt(&"renamed".cyan(), f),
+            FileAction::Rewritten => fmt::Display::fmt(&"rewritten".yellow(), f),
+            FileAction::Skipped => fmt::Display::fmt(&"skipped".dimmed(), f),
+        }
+    }
+}
+
+impl fmt::Display for PlanOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            write!(
+                f,
+                "{:>9}  {:>10}  {}",
+                file.action,
+                format_size(file.size).dimmed(),
+                file.path
+            )?;
+            if matches!(file.action, FileAction::Skipped) {
+                write!(f, " {}", "(already exists)".dimmed())?;
+            } else if file.source != file.path {
+                write!(f, " {}", format!("(from {})", file.source).dimmed())?;
+            }
+            writeln!(f)?;
+        }
+        let skipped = self
+            .files
+            .iter()
+            .filter(|file| matches!(file.action, FileAction::Skipped))
+            .count();
+        write!(
+            f,
+            "{} files, {}",
+            self.files.len() - skipped,
+            format_size(self.total_size)
+        )?;
+        if skipped > 0 {
+            write!(f, ", {skipped} skipped")?;
+        }
+        Ok(())
+    }
+}
+
+/// Print the files a plan would write, without writing them.
+pub(crate) fn print(global_args: &GlobalArgs, template: &Path, plan: &Plan) -> CommandResult {
+    let output = PlanOutput::new(template, plan)?;
+
+    match global_args.format {
+        OutputFormats::Text => {
+            println!("{output}");
+        }
+        OutputFormats::Json => {
+            let json = serde_json::to_string_pretty(&output)?;
+            println!("{json}");
+        }
+        #[cfg(feature = "schema")]
+        OutputFormats::JsonSchema => {
+            let schema = schemars::schema_for!(PlanOutput);
+            let json = serde_json::to_string_pretty(&schema)?;
+            println!("{json}")
+        }
+    };
+
+    Ok(())
+}
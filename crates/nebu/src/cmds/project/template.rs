@@ -0,0 +1,288 @@
+use std::hash::Hash;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use git2::{Oid, Repository};
+use nebu_cache::{
+    ArchiveCache, ArchiveFormat, CacheEntry, CacheLock, CacheManager, LocalCache, Refresh,
+    RepoCache,
+};
+use nebu_template::{TemplateKind, TemplateLock};
+use owo_colors::OwoColorize;
+use tempfile::{TempDir, tempdir};
+
+use crate::error::{CommandError, CommandResult};
+
+/// A fetched template repository, either in the cache or in a scratch clone.
+///
+/// Cached templates stay locked until this is dropped, so other processes
+/// can't refresh the cache while it is checked out.
+pub(crate) struct FetchedTemplate {
+    location: PathBuf,
+    _scratch: Option<TempDir>,
+    _lock: Option<CacheLock>,
+}
+
+impl FetchedTemplate {
+    /// Check out the template into `path`, limited to the sparse paths of
+    /// `repo`.
+    ///
+    /// Checks out the pinned revision of the repository if any, otherwise the
+    /// head of the tracked branch. Returns the OID of the commit that was
+    /// checked out.
+    pub(crate) fn checkout(&self, repo: &RepoCache, path: &Path) -> CommandResult<Oid> {
+        let oid = repo
+            .checkout_into(&self.location, path)
+            .map_err(CommandError::from_cache)?;
+        tracing::debug!("checked out template at commit {oid}");
+        Ok(oid)
+    }
+}
+
+/// Fetch the template repository into the cache, or into a scratch directory
+/// if the cache is disabled.
+pub(crate) fn fetch_template(
+    global_args: &crate::GlobalArgs,
+    project_args: &super::ProjectArgs,
+    repo: &RepoCache,
+) -> CommandResult<FetchedTemplate> {
+    if project_args.no_cache {
+        if global_args.offline {
+            return Err(CommandError::offline(
+                "clone the template without the cache",
+            ));
+        }
+        let scratch = tempdir()?;
+        repo.clone_repository(scratch.path())
+            .map_err(CommandError::from_cache)?;
+        return Ok(FetchedTemplate {
+            location: scratch.path().to_path_buf(),
+            _scratch: Some(scratch),
+            _lock: None,
+        });
+    }
+
+    std::fs::create_dir_all(&global_args.cache_path)?;
+
+    let mut cache = CacheManager::new(global_args.cache_path.clone(), repo.clone())
+        .with_policy(project_args.freshness_policy(global_args)?)
+        .with_offline(global_args.offline);
+    if global_args.offline && !cache.is_complete() {
+        let Some(entry) = find_offline_copy(global_args, repo)? else {
+            return Err(CommandError::offline(format!("fetch {}", repo.repo)));
+        };
+        tracing::info!("using cache entry {} while offline", entry.id);
+        cache = CacheManager::open(entry.location, repo.clone()).with_offline(true);
+    }
+    let lock = lock_and_refresh(&mut cache, global_args)?;
+
+    Ok(FetchedTemplate {
+        location: cache.location().to_path_buf(),
+        _scratch: None,
+        _lock: Some(lock),
+    })
+}
+
+/// Lock the cache entry and refresh it if it is not fresh.
+fn lock_and_refresh<T: Refresh + Hash>(
+    cache: &mut CacheManager<T>,
+    global_args: &crate::GlobalArgs,
+) -> CommandResult<CacheLock> {
+    tracing::debug!("using template cache at {}", cache.location().display());
+
+    let timeout = Duration::from_secs(global_args.lock_timeout);
+    let lock = cache
+        .lock(timeout, |pid| match pid {
+            Some(pid) => eprintln!("{} for lock held by pid {pid}", "waiting".yellow()),
+            None => eprintln!("{} for lock on the template cache", "waiting".yellow()),
+        })
+        .map_err(CommandError::from_cache)?;
+
+    if cache.try_refresh().map_err(CommandError::from_cache)? {
+        tracing::info!("refreshed cached template {}", cache.inner().source().url);
+    }
+    Ok(lock)
+}
+
+/// Find a complete cache entry of the same repository that contains the
+/// revision `repo` points to.
+///
+/// Entries of a repository differ by branch, pin and depth, so an entry for
+/// another of those might still serve a request when offline.
+fn find_offline_copy(
+    global_args: &crate::GlobalArgs,
+    repo: &RepoCache,
+) -> CommandResult<Option<CacheEntry>> {
+    let entries = CacheEntry::list(&global_args.cache_path).map_err(CommandError::from_cache)?;
+    Ok(entries.into_iter().find(|entry| {
+        entry.complete
+            && entry
+                .metadata
+                .as_ref()
+                .is_some_and(|metadata| metadata.source.url == repo.repo)
+            && Repository::open(&entry.location).is_ok_and(|cached| repo.resolve(&cached).is_ok())
+    }))
+}
+
+/// Fetch the template repository and check it out into `path`.
+///
+/// Returns the OID of the commit that was checked out.
+pub(crate) fn checkout_template(
+    path: &Path,
+    global_args: &crate::GlobalArgs,
+    project_args: &super::ProjectArgs,
+    repo: &RepoCache,
+) -> CommandResult<Oid> {
+    fetch_template(global_args, project_args, repo)?.checkout(repo, path)
+}
+
+/// A template on the local filesystem, given with `--template`.
+///
+/// Local templates need no network, so they can be used offline, and are read
+/// as a whole instead of sparsely.
+#[derive(Debug, Clone)]
+pub(crate) struct TemplateSource {
+    /// Path of the template as it was given, which is recorded in the
+    /// lockfile.
+    path: PathBuf,
+    kind: LocalTemplate,
+}
+
+#[derive(Debug, Clone)]
+enum LocalTemplate {
+    /// A template directory, read including uncommitted changes.
+    Directory(LocalCache),
+    /// A `.tar.gz` or `.zip` archive of a template.
+    Archive(ArchiveCache),
+}
+
+impl TemplateSource {
+    /// Parse a path or `file://` URL to a template directory or archive.
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        let path = PathBuf::from(value.strip_prefix("file://").unwrap_or(value));
+        let resolved =
+            std::fs::canonicalize(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+        Self::new(path, resolved)
+    }
+
+    fn new(path: PathBuf, resolved: PathBuf) -> Result<Self, String> {
+        let kind = if resolved.is_dir() {
+            LocalTemplate::Directory(LocalCache::new(resolved))
+        } else if let Some(format) = ArchiveFormat::from_path(&resolved) {
+            LocalTemplate::Archive(ArchiveCache::new(resolved, format))
+        } else {
+            return Err(format!(
+                "{}: expected a directory, .tar.gz, .tgz or .zip",
+                path.display()
+            ));
+        };
+        Ok(TemplateSource { path, kind })
+    }
+
+    /// The local template the project in `project_dir` was generated from,
+    /// if it was not generated from a git repository.
+    ///
+    /// Relative paths in the lockfile are relative to the project.
+    pub(crate) fn from_lock(
+        template: &TemplateLock,
+        project_dir: &Path,
+    ) -> CommandResult<Option<Self>> {
+        if template.kind == TemplateKind::Git {
+            return Ok(None);
+        }
+
+        let path = PathBuf::from(&template.url);
+        let Ok(resolved) = project_dir.join(&path).canonicalize() else {
+            return Err(anyhow::anyhow!(
+                "the template of the project at {} does not exist",
+                path.display()
+            )
+            .into());
+        };
+        Self::new(path, resolved)
+            .map(Some)
+            .map_err(|err| anyhow::anyhow!(err).into())
+    }
+
+    /// Describe the template for the lockfile of the project in
+    /// `project_dir`.
+    ///
+    /// Relative paths are made relative to the project, so the lockfile
+    /// works wherever the project and the template are checked out together.
+    pub(crate) fn lock(&self, project_dir: &Path) -> CommandResult<TemplateLock> {
+        let kind = match self.kind {
+            LocalTemplate::Directory(_) => TemplateKind::Directory,
+            LocalTemplate::Archive(_) => TemplateKind::Archive,
+        };
+        let path = if self.path.is_absolute() {
+            self.path.clone()
+        } else {
+            relative_path(
+                &std::path::absolute(&self.path)?,
+                &std::path::absolute(project_dir)?,
+            )
+        };
+
+        Ok(TemplateLock {
+            kind,
+            url: path.display().to_string(),
+            branch: String::new(),
+            remote: String::new(),
+            tag: None,
+            commit: String::new(),
+        })
+    }
+
+    /// Copy all of the template into `path`.
+    ///
+    /// Directories are read directly, while archives are unpacked into the
+    /// cache first.
+    pub(crate) fn checkout(
+        &self,
+        path: &Path,
+        global_args: &crate::GlobalArgs,
+        project_args: &super::ProjectArgs,
+    ) -> CommandResult {
+        match &self.kind {
+            LocalTemplate::Directory(local) => {
+                local.copy_into(path).map_err(CommandError::from_cache)?;
+            }
+            LocalTemplate::Archive(archive) if project_args.no_cache => {
+                archive
+                    .clone()
+                    .refresh(path)
+                    .map_err(CommandError::from_cache)?;
+            }
+            LocalTemplate::Archive(archive) => {
+                std::fs::create_dir_all(&global_args.cache_path)?;
+                let mut cache = CacheManager::new(global_args.cache_path.clone(), archive.clone());
+                let _lock = lock_and_refresh(&mut cache, global_args)?;
+                archive
+                    .checkout_into(cache.location(), path)
+                    .map_err(CommandError::from_cache)?;
+            }
+        }
+        tracing::debug!("checked out template {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// The path of `path` relative to `base`, where both are absolute.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut relative: PathBuf = base
+        .components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(path.components().skip(common));
+    relative
+}
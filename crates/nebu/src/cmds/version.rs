@@ -59,7 +59,7 @@ impl From<VersionInfo> for clap::builder::Str {
     }
 }
 
-pub(crate) fn run(global_args: Box<GlobalArgs>) -> CommandResult {
+pub(crate) fn run(global_args: &GlobalArgs) -> CommandResult {
     tracing::trace!("running version command");
 
     let commit_info = option_env!("NEBU_COMMIT_HASH")
@@ -26,6 +26,74 @@ pub(crate) enum CommandErrorKind {
         url("https://nebu.lerpz.com/docs/cli/errors#arguments")
     )]
     IoError(#[from] std::io::Error),
+    /// The template or its manifest is invalid.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Template(#[from] nebu_template::Error),
+    /// The nebu configuration is invalid.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Config(#[from] nebu_config::Error),
+    /// None of the credentials were accepted by the remote.
+    #[error("failed to authenticate with {url}")]
+    #[diagnostic(
+        code(command::authentication_failed),
+        help(
+            "Tried {tried}. Configure an SSH key or a token in the `credentials` \
+            section of the nebu configuration, or set `NEBU_GIT_TOKEN`"
+        ),
+        url("https://nebu.lerpz.com/docs/cli/errors#authentication_failed")
+    )]
+    Authentication {
+        url: String,
+        tried: String,
+        #[source]
+        source: git2::Error,
+    },
+    /// The command needs the network, but nebu runs in offline mode.
+    #[error("cannot {action} in offline mode")]
+    #[diagnostic(
+        code(command::offline),
+        help(
+            "Only templates that are already in the cache can be used offline. \
+            Run without `--offline` and unset `NEBU_OFFLINE` once the network \
+            is available"
+        ),
+        url("https://nebu.lerpz.com/docs/cli/errors#offline")
+    )]
+    Offline { action: String },
+    /// Another process held the lock on the cache for too long.
+    #[error("timed out waiting for the lock on {}", path.display())]
+    #[diagnostic(
+        code(command::lock_timeout),
+        help(
+            "{} is using the template cache. Wait for it to finish or raise \
+            `--lock-timeout`",
+            pid.map_or("Another process".to_string(), |pid| format!("The process with pid {pid}"))
+        ),
+        url("https://nebu.lerpz.com/docs/cli/errors#lock_timeout")
+    )]
+    LockTimeout {
+        path: std::path::PathBuf,
+        pid: Option<u32>,
+    },
+    /// A post-generation hook of the template failed.
+    #[error("the hook `{name}` failed with {status}")]
+    #[diagnostic(
+        code(command::hook_failed),
+        help(
+            "The project was generated, but the remaining hooks were not run. \
+            Fix the problem and run `{command}` in {} manually",
+            dir.display()
+        ),
+        url("https://nebu.lerpz.com/docs/cli/errors#hook_failed")
+    )]
+    HookFailed {
+        name: String,
+        command: String,
+        dir: std::path::PathBuf,
+        status: std::process::ExitStatus,
+    },
     /// All other errors that do not fit into a specific category.
     #[error(transparent)]
     #[diagnostic(
@@ -43,6 +111,53 @@ impl CommandError {
             inner: CommandErrorKind::GitError(err),
         }
     }
+
+    /// Create a new [`CommandError`] from a [`nebu_template::Error`].
+    pub fn from_template(err: nebu_template::Error) -> Self {
+        CommandError {
+            inner: CommandErrorKind::Template(err),
+        }
+    }
+
+    /// Create a new [`CommandError`] for an `action` that needs the network.
+    pub fn offline(action: impl Into<String>) -> Self {
+        CommandError {
+            inner: CommandErrorKind::Offline {
+                action: action.into(),
+            },
+        }
+    }
+
+    /// Create a new [`CommandError`] from a [`nebu_config::Error`].
+    pub fn from_config(err: nebu_config::Error) -> Self {
+        CommandError {
+            inner: CommandErrorKind::Config(err),
+        }
+    }
+
+    /// Create a new [`CommandError`] from a [`nebu_cache::Error`].
+    pub fn from_cache(err: nebu_cache::Error) -> Self {
+        let inner = match err {
+            nebu_cache::Error::Git(err) => CommandErrorKind::GitError(err),
+            nebu_cache::Error::Authentication { url, tried, source } => {
+                let tried = if tried.is_empty() {
+                    "no credentials".to_string()
+                } else {
+                    tried.join(", ")
+                };
+                CommandErrorKind::Authentication { url, tried, source }
+            }
+            nebu_cache::Error::LockTimeout { path, pid } => {
+                CommandErrorKind::LockTimeout { path, pid }
+            }
+            nebu_cache::Error::Offline { url } => CommandErrorKind::Offline {
+                action: format!("fetch {url}"),
+            },
+            nebu_cache::Error::Io(err) => CommandErrorKind::IoError(err),
+            nebu_cache::Error::Other(err) => CommandErrorKind::Other(err),
+        };
+        CommandError { inner }
+    }
 }
 
 impl<E> From<E> for CommandError
@@ -1,3 +1,4 @@
+use std::io::IsTerminal;
 use std::path::PathBuf;
 
 use clap::{Parser, crate_authors, crate_version};
@@ -41,7 +42,11 @@ enum Commands {
     ///
     /// Creates new project, manage existing projects, or perform other
     /// project-related tasks.
-    Project(cmds::project::Project),
+    Project(Box<cmds::project::Project>),
+    /// Cache subcommands
+    ///
+    /// Inspect, refresh and clean up the cached template repositories.
+    Cache(cmds::cache::Cache),
     /// Infrastructure subcommands
     ///
     /// Manage infrastructure resources, such as key vaults, databases and more.
@@ -92,6 +97,29 @@ struct GlobalArgs {
     )]
     cache_path: PathBuf,
 
+    /// Seconds to wait for another nebu process using a cache entry.
+    #[arg(
+        global = true,
+        long,
+        env = "NEBU_LOCK_TIMEOUT",
+        value_name = "SECONDS",
+        default_value = "300"
+    )]
+    lock_timeout: u64,
+
+    /// Never contact remote repositories.
+    ///
+    /// Cached templates are used as they are, without checking for changes.
+    /// Commands that need the network fail early instead.
+    #[arg(
+        global = true,
+        long,
+        env = "NEBU_OFFLINE",
+        default_value = "false",
+        value_parser = clap::builder::BoolishValueParser::new(),
+    )]
+    offline: bool,
+
     /// Enable verbose output.
     ///
     /// This will enable more detailed logging output, which can be useful for
@@ -117,6 +145,24 @@ struct GlobalArgs {
     verbose: u8,
 }
 
+impl GlobalArgs {
+    /// Read the nebu configuration from the configuration directory.
+    fn load_config(&self) -> error::CommandResult<nebu_config::Config> {
+        nebu_config::Config::load_from_dir(&self.config_path)
+            .map_err(error::CommandError::from_config)
+    }
+
+    /// Whether to show progress bars for long running operations.
+    ///
+    /// Progress is only shown for text output on a terminal, so it never ends
+    /// up in redirected or machine readable output.
+    fn show_progress(&self) -> bool {
+        self.format == OutputFormats::Text
+            && std::io::stdout().is_terminal()
+            && std::io::stderr().is_terminal()
+    }
+}
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
 enum OutputFormats {
     #[default]
@@ -170,9 +216,10 @@ async fn main() -> miette::Result<()> {
     }
 
     let result = match cli.command {
-        Commands::Version => cmds::version::run(cli.global_args),
+        Commands::Version => cmds::version::run(&cli.global_args),
         Commands::Env(_env) => todo!(),
-        Commands::Project(project) => cmds::project::run(project, cli.global_args).await,
+        Commands::Project(project) => cmds::project::run(*project, cli.global_args).await,
+        Commands::Cache(cache) => cmds::cache::run(cache, &cli.global_args),
         Commands::Infra => todo!(),
     };
 